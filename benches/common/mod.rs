@@ -0,0 +1,36 @@
+//! Helpers shared by the benchmarks. Each benchmark is a plain binary run by
+//! `cargo bench`, printing the mean time per iteration of each case. The
+//! generators are the integration tests' own, so that every run measures the
+//! same inputs the tests check.
+
+#![allow(dead_code)]
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use itc::Event;
+
+#[path = "../../tests/common/mod.rs"]
+mod shared;
+
+pub use self::shared::*;
+
+/// Runs `f` `iters` times and prints the mean time per run.
+pub fn bench<T, F: FnMut() -> T>(name: &str, iters: u32, mut f: F) {
+    let start = Instant::now();
+
+    for _ in 0..iters {
+        black_box(f());
+    }
+
+    let per = start.elapsed() / iters;
+    println!("{:<40} {:>12?}/iter", name, per);
+}
+
+/// A tree `depth` levels deep with a random leaf hanging off each level, in
+/// normal form.
+pub fn comb_event(rng: &mut Rng, depth: u32) -> Event {
+    (0..depth).fold(Event::Leaf(rng.below(8) as i64), |tail, _| {
+        Event::node(0, Event::Leaf(rng.below(8) as i64), tail)
+    })
+}
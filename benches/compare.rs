@@ -0,0 +1,83 @@
+//! Comparing one clock against every peer, round after round, directly and
+//! through the caching comparators, for peers close to the clock and for
+//! peers far ahead of or behind it.
+
+extern crate itc;
+
+mod common;
+
+use itc::{DigestCache, Event, Stamp, StampComparator};
+
+use common::{Rng, balanced_event, bench};
+
+fn main() {
+    let mut rng = Rng(208);
+    let base = balanced_event(&mut rng, 10);
+
+    // Peers that have each heard one more region's worth of history, and some
+    // that are well ahead or behind everyone.
+    let mut peers: Vec<Stamp> = Stamp::seed().fork_shards(64).into_iter()
+        .map(|s| Stamp::new(s.id.clone(), base.clone()).event())
+        .collect();
+    for i in 0..16 {
+        let far = Stamp::new(peers[i].id.clone(), balanced_event(&mut rng, 10));
+        peers.push(far);
+    }
+    let mut with_me = peers.clone();
+    with_me.push(peers[0].join(&peers[1].peek()));
+    let me = with_me.len() - 1;
+
+    bench("100 rounds, direct leq", 1, || {
+        let mut n = 0;
+        for _ in 0..100 {
+            for peer in &peers {
+                n += with_me[me].event.leq(&peer.event) as usize;
+                n += peer.event.leq(&with_me[me].event) as usize;
+            }
+        }
+        n
+    });
+
+    bench("100 rounds, StampComparator", 1, || {
+        let mut comparator = StampComparator::new(&with_me);
+        let mut n = 0;
+        for _ in 0..100 {
+            for i in 0..peers.len() {
+                n += comparator.leq(me, i) as usize;
+                n += comparator.leq(i, me) as usize;
+            }
+        }
+        n
+    });
+
+    let mut cache = DigestCache::new();
+    for peer in &peers {
+        cache.insert(peer);
+    }
+
+    bench("100 rounds, DigestCache::leq_each", 1, || {
+        (0..100).map(|_| cache.leq_each(&with_me[me]).len()).sum::<usize>()
+    });
+
+    let far: Vec<Stamp> = peers.iter()
+        .map(|peer| Stamp::new(peer.id.clone(), Event::Leaf(100).join(&peer.event)))
+        .collect();
+    let mut far_cache = DigestCache::new();
+    for peer in &far {
+        far_cache.insert(peer);
+    }
+
+    bench("100 rounds, direct leq, far peers", 1, || {
+        let mut n = 0;
+        for _ in 0..100 {
+            for peer in &far {
+                n += with_me[me].event.leq(&peer.event) as usize;
+            }
+        }
+        n
+    });
+
+    bench("100 rounds, DigestCache::leq_each, far peers", 1, || {
+        (0..100).map(|_| far_cache.leq_each(&with_me[me]).len()).sum::<usize>()
+    });
+}
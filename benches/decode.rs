@@ -0,0 +1,29 @@
+//! Decoding throughput, from slices and from readers.
+
+extern crate itc;
+
+mod common;
+
+use std::io::Cursor;
+
+use itc::Event;
+
+use common::{Rng, bench, random_event};
+
+fn main() {
+    let mut rng = Rng(233);
+    let blobs: Vec<Vec<u8>> = (0..2000)
+        .map(|_| random_event(&mut rng, 12).norm().encode())
+        .collect();
+    let bytes: usize = blobs.iter().map(|b| b.len()).sum();
+    println!("{} events, {} bytes", blobs.len(), bytes);
+
+    bench("decode from slices", 20, || {
+        blobs.iter().map(|b| Event::decode(b).unwrap().size()).sum::<usize>()
+    });
+    bench("decode from readers", 20, || {
+        blobs.iter()
+            .map(|b| Event::decode_from(&mut Cursor::new(b)).unwrap().size())
+            .sum::<usize>()
+    });
+}
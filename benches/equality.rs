@@ -0,0 +1,22 @@
+//! Event equality on unequal pairs, which mostly settle on their lowest and
+//! highest counts, on equal pairs, which are normalized and compared, and on
+//! clones, which share their children.
+
+extern crate itc;
+
+mod common;
+
+use common::{Rng, bench, random_event};
+
+fn main() {
+    let mut rng = Rng(230);
+    let pairs: Vec<_> = (0..1000)
+        .map(|_| (random_event(&mut rng, 10), random_event(&mut rng, 10)))
+        .collect();
+    let same: Vec<_> = pairs.iter().map(|(a, _)| (a.clone(), a.norm())).collect();
+
+    bench("eq, unequal pairs", 100, || pairs.iter().filter(|(a, b)| a == b).count());
+    bench("eq, equal pairs", 100, || same.iter().filter(|(a, b)| a == b).count());
+    let clones: Vec<_> = pairs.iter().map(|(a, _)| (a.clone(), a.clone())).collect();
+    bench("eq, clones", 100, || clones.iter().filter(|(a, b)| a == b).count());
+}
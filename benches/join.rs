@@ -0,0 +1,45 @@
+//! Joins of large events: where only one region differs, the result shares
+//! the rest with the inputs, and how long joins take as trees deepen. Such a
+//! join only walks the path down to that region, so it should stay far below
+//! the cost of normalizing the whole tree.
+
+extern crate itc;
+
+mod common;
+
+use itc::Event;
+
+use common::{Rng, balanced_event, bench, comb_event, random_event, slot_ident};
+
+fn main() {
+    let mut rng = Rng(219);
+
+    for &depth in &[8, 12, 16] {
+        let before = balanced_event(&mut rng, depth);
+        let after = before.event(&slot_ident(depth, 1 << (depth - 1)));
+        let joined = before.join(&after);
+
+        let inputs = Event::unique_node_count(&[&before, &after]);
+        let total = Event::unique_node_count(&[&before, &after, &joined]);
+        println!("depth {:>2}, one region differs: {} nodes, {} new in the join",
+                 depth, joined.size(), total - inputs);
+
+        let name = format!("join, depth {}, one region differs", depth);
+        bench(&name, 100, || before.join(&after));
+        bench(&format!("norm, depth {}", depth), 100, || after.norm());
+        let other = balanced_event(&mut rng, depth);
+        bench(&format!("join, depth {}, concurrent", depth), 20, || before.join(&other));
+    }
+
+    for &depth in &[100, 500, 2000] {
+        let (a, b) = (comb_event(&mut rng, depth), comb_event(&mut rng, depth));
+        bench(&format!("join, comb depth {}", depth), 100, || a.join(&b));
+    }
+
+    let pairs: Vec<_> = (0..1000)
+        .map(|_| (random_event(&mut rng, 16).norm(), random_event(&mut rng, 16).norm()))
+        .collect();
+    bench("join, random depth 16 pairs", 20, || {
+        pairs.iter().map(|(a, b)| a.join(b).size()).sum::<usize>()
+    });
+}
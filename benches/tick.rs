@@ -0,0 +1,46 @@
+//! A million ticks, in place and functionally, with ids of growing depth, and
+//! tracked ticks on events of growing size, which should cost the same.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, Ident, TrackedEvent};
+
+use common::{Rng, balanced_event, bench, slot_ident};
+
+fn main() {
+    for &depth in &[1, 4, 8] {
+        let id = slot_ident(depth, 0);
+
+        bench(&format!("1M ticks in place, id depth {}", depth), 1, || {
+            let mut e = Event::seed();
+            for _ in 0..1_000_000 {
+                e.event_in_place(&id);
+            }
+            e
+        });
+
+        bench(&format!("1M ticks by value, id depth {}", depth), 1, || {
+            (0..1_000_000).fold(Event::seed(), |e, _| e.event(&id))
+        });
+    }
+
+    bench("1M ticks in place, whole id", 1, || {
+        let mut e = Event::seed();
+        for _ in 0..1_000_000 {
+            e.event_in_place(&Ident::One);
+        }
+        e
+    });
+
+    let mut rng = Rng(241);
+    for &depth in &[8, 12, 16] {
+        let mut tracked = TrackedEvent::new(&balanced_event(&mut rng, depth));
+        let id = slot_ident(depth, 1);
+        bench(&format!("tracked tick, event depth {}", depth), 1000, || {
+            tracked.tick(&id);
+            tracked.max()
+        });
+    }
+}
@@ -0,0 +1,25 @@
+#![no_main]
+
+use itc::{Event, Ident, Stamp};
+use libfuzzer_sys::fuzz_target;
+
+// Whatever decodes must re-encode to bytes that decode to the same value and
+// re-encode identically; the input itself may use non-minimal varints.
+fuzz_target!(|data: &[u8]| {
+    if let Ok(e) = Event::decode(data) {
+        let bytes = e.encode();
+        let again = Event::decode(&bytes).expect("re-encoded event must decode");
+        assert_eq!(again.encode(), bytes);
+    }
+
+    if let Ok(i) = Ident::decode(data) {
+        let bytes = i.encode();
+        assert_eq!(Ident::decode(&bytes).expect("re-encoded id must decode"), i);
+    }
+
+    if let Ok(s) = Stamp::decode(data) {
+        let bytes = s.encode();
+        let again = Stamp::decode(&bytes).expect("re-encoded stamp must decode");
+        assert_eq!(again.encode(), bytes);
+    }
+});
@@ -0,0 +1,121 @@
+//! Measuring how a membership protocol treats the id space.
+//!
+//! Replaying a recorded sequence of forks and joins shows how evenly it deals
+//! out the id space and how deep it drives the id trees, so that protocols
+//! which leave some members with slivers can be caught before deployment. A
+//! live cluster's ids can be checked for lost regions the same way.
+
+use Ident;
+use Stamp;
+
+/// One step of a recorded membership history. Members are numbered in order
+/// of creation, starting from the seed as member 0, and keep their numbers
+/// while others leave.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ForkOp {
+    /// The member forks, keeping one half and handing the other to a new
+    /// member numbered after all the others.
+    Fork(usize),
+    /// The second member leaves, handing its id to the first.
+    Join(usize, usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FairnessReport {
+    /// The share of the id space each remaining member owns, in order of
+    /// their numbers.
+    pub shares: Vec<f64>,
+    /// The variance of `shares`.
+    pub variance: f64,
+    /// The largest share divided by the smallest.
+    pub imbalance: f64,
+    /// The depth of the deepest remaining id tree.
+    pub max_depth: usize,
+}
+
+impl FairnessReport {
+    /// Whether some member owns more than `factor` times the share of
+    /// another.
+    pub fn is_unfair(&self, factor: f64) -> bool {
+        self.imbalance > factor
+    }
+}
+
+/// Replays `ops` from a single seed and reports on the resulting ids.
+///
+/// Panics if an op names a member that does not exist or has left, or joins
+/// a member with itself.
+pub fn fork_fairness(ops: &[ForkOp]) -> FairnessReport {
+    let mut members: Vec<Option<Ident>> = vec![Some(Ident::seed())];
+
+    for op in ops {
+        match *op {
+            ForkOp::Fork(m) => {
+                let (kept, given) = member(&members, m).split();
+                members[m] = Some(kept);
+                members.push(Some(given));
+            },
+            ForkOp::Join(heir, departing) => {
+                assert!(heir != departing, "member {} cannot join itself", heir);
+                let joined = member(&members, heir).sum(member(&members, departing));
+                members[heir] = Some(joined);
+                members[departing] = None;
+            },
+        }
+    }
+
+    let ids: Vec<&Ident> = members.iter().flatten().collect();
+    let shares: Vec<f64> = ids.iter().map(|id| share(id)).collect();
+
+    let mean = shares.iter().sum::<f64>() / shares.len() as f64;
+    let variance = shares.iter().map(|s| (s - mean) * (s - mean)).sum::<f64>()
+        / shares.len() as f64;
+    let largest = shares.iter().cloned().fold(0.0, f64::max);
+    let smallest = shares.iter().cloned().fold(1.0, f64::min);
+
+    FairnessReport {
+        variance,
+        imbalance: largest / smallest,
+        max_depth: ids.iter().map(|id| id.canonical().depth()).max().unwrap_or(0),
+        shares,
+    }
+}
+
+/// How a cluster's live stamps cover the id space.
+#[derive(Clone, Debug, PartialEq)]
+pub struct IdSpaceReport {
+    /// The share of the id space owned by at least one stamp.
+    pub owned: f64,
+    /// The share owned by none, such as the ids of nodes that left without
+    /// handing them back.
+    pub lost: f64,
+    /// The number of maximal regions making up the stamps' ids, added up.
+    pub regions: usize,
+    /// The depth of the deepest id tree.
+    pub max_depth: usize,
+}
+
+/// Summarizes how well the ids of the live `stamps` cover the id space.
+pub fn id_space_report(stamps: &[Stamp]) -> IdSpaceReport {
+    let ids: Vec<Ident> = stamps.iter().map(|s| s.id.canonical()).collect();
+    let owned = share(&Ident::least_upper_id(&ids));
+
+    IdSpaceReport {
+        owned,
+        lost: 1.0 - owned,
+        regions: ids.iter().map(|id| id.regions().len()).sum(),
+        max_depth: ids.iter().map(|id| id.depth()).max().unwrap_or(0),
+    }
+}
+
+fn member(members: &[Option<Ident>], m: usize) -> &Ident {
+    match members.get(m) {
+        Some(Some(id)) => id,
+        _ => panic!("member {} does not exist", m),
+    }
+}
+
+// The fraction of the id space the id owns.
+fn share(id: &Ident) -> f64 {
+    id.regions().iter().map(|r| 0.5f64.powi(r.depth() as i32)).sum()
+}
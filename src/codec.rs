@@ -0,0 +1,66 @@
+//! Framing stamps on `tokio` codec pipelines.
+//!
+//! Each frame is a big-endian `u32` length followed by the compact encoding of
+//! one stamp.
+
+use std::io;
+
+use bytes::{Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+use Stamp;
+
+pub struct StampCodec {
+    frames: LengthDelimitedCodec,
+}
+
+impl StampCodec {
+    pub fn new() -> StampCodec {
+        StampCodec {
+            frames: LengthDelimitedCodec::new(),
+        }
+    }
+
+    /// Sets the largest frame the decoder accepts, 8 MiB by default.
+    pub fn with_max_frame_length(max: usize) -> StampCodec {
+        StampCodec {
+            frames: LengthDelimitedCodec::builder().max_frame_length(max).new_codec(),
+        }
+    }
+}
+
+impl Default for StampCodec {
+    fn default() -> StampCodec {
+        StampCodec::new()
+    }
+}
+
+impl Encoder<Stamp> for StampCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, stamp: Stamp, dst: &mut BytesMut) -> io::Result<()> {
+        self.encode(&stamp, dst)
+    }
+}
+
+impl<'a> Encoder<&'a Stamp> for StampCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, stamp: &'a Stamp, dst: &mut BytesMut) -> io::Result<()> {
+        self.frames.encode(Bytes::from(stamp.encode()), dst)
+    }
+}
+
+impl Decoder for StampCodec {
+    type Item = Stamp;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> io::Result<Option<Stamp>> {
+        match self.frames.decode(src)? {
+            Some(frame) => Stamp::decode(&frame)
+                .map(Some)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use Event;
+use Stamp;
+
+/// Compares stamps from a fixed working set, normalizing each event at most
+/// once however many comparisons it takes part in. The stamps are borrowed,
+/// so cannot change: each `leq` between two of them is worked out once and
+/// remembered, and each event's lowest and highest counts settle most `leq`
+/// questions between events far apart without walking either tree.
+pub struct StampComparator<'a> {
+    stamps: Vec<&'a Stamp>,
+    normal: Vec<Option<Event>>,
+    bounds: Vec<Option<(i64, i64)>>,
+    leq: HashMap<(usize, usize), bool>,
+}
+
+impl<'a> StampComparator<'a> {
+    pub fn new(stamps: &'a [Stamp]) -> StampComparator<'a> {
+        StampComparator {
+            stamps: stamps.iter().collect(),
+            normal: stamps.iter().map(|_| None).collect(),
+            bounds: stamps.iter().map(|_| None).collect(),
+            leq: HashMap::new(),
+        }
+    }
+
+    /// Adds a stamp to the working set, returning its index.
+    pub fn push(&mut self, stamp: &'a Stamp) -> usize {
+        self.stamps.push(stamp);
+        self.normal.push(None);
+        self.bounds.push(None);
+        self.stamps.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.stamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stamps.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> &'a Stamp {
+        self.stamps[i]
+    }
+
+    pub fn leq(&mut self, i: usize, j: usize) -> bool {
+        if let Some(&known) = self.leq.get(&(i, j)) {
+            return known;
+        }
+
+        let (a, b) = (self.bounds(i), self.bounds(j));
+        let leq = leq_by_bounds(a, b)
+            .unwrap_or_else(|| self.stamps[i].event.leq(&self.stamps[j].event));
+        self.leq.insert((i, j), leq);
+        leq
+    }
+
+    pub fn equal(&mut self, i: usize, j: usize) -> bool {
+        self.normalize(i);
+        self.normalize(j);
+        self.normal(i).eq_real(self.normal(j))
+    }
+
+    pub fn concurrent(&mut self, i: usize, j: usize) -> bool {
+        !self.leq(i, j) && !self.leq(j, i)
+    }
+
+    fn normalize(&mut self, i: usize) {
+        if self.normal[i].is_none() {
+            self.normal[i] = Some(self.stamps[i].event.norm());
+        }
+    }
+
+    fn normal(&self, i: usize) -> &Event {
+        self.normal[i].as_ref().unwrap()
+    }
+
+    fn bounds(&mut self, i: usize) -> (i64, i64) {
+        let event = &self.stamps[i].event;
+        *self.bounds[i].get_or_insert_with(|| (event.min(), event.max()))
+    }
+}
+
+// Settles `a.leq(b)` from the events' lowest and highest counts when they
+// suffice: `a` is below `b` everywhere if its highest count is at most `b`'s
+// lowest, and cannot be if either of its bounds exceeds `b`'s.
+pub(crate) fn leq_by_bounds(a: (i64, i64), b: (i64, i64)) -> Option<bool> {
+    if a.1 <= b.0 {
+        Some(true)
+    } else if a.0 > b.0 || a.1 > b.1 {
+        Some(false)
+    } else {
+        None
+    }
+}
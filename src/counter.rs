@@ -0,0 +1,75 @@
+//! A grow-only counter replicated over ITC ids.
+//!
+//! Each replica counts its increments against the first region its id owns,
+//! which no other replica owns at the same time. A region that is forked away
+//! keeps the count it reached, and is picked up again, count and all, by
+//! whichever replica joins it back together. The counter's value is the sum
+//! over every region seen, and merging takes the larger count for each.
+
+use std::cmp;
+use std::collections::BTreeMap;
+
+use IdentPath;
+use Stamp;
+
+#[derive(Clone, Debug)]
+pub struct GCounter {
+    stamp: Stamp,
+    counts: BTreeMap<IdentPath, u64>,
+}
+
+impl GCounter {
+    pub fn new(stamp: Stamp) -> GCounter {
+        GCounter { stamp, counts: BTreeMap::new() }
+    }
+
+    pub fn seed() -> GCounter {
+        GCounter::new(Stamp::seed())
+    }
+
+    pub fn stamp(&self) -> &Stamp {
+        &self.stamp
+    }
+
+    pub fn value(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    /// Counts `by` more in this replica's region, ticking its stamp. Panics if
+    /// the counter's id is anonymous.
+    pub fn increment(&mut self, by: u64) {
+        let region = self.stamp.id.regions().into_iter().next()
+            .expect("an anonymous counter cannot increment");
+
+        *self.counts.entry(region).or_insert(0) += by;
+        self.stamp = self.stamp.event();
+    }
+
+    pub fn fork(&self) -> (GCounter, GCounter) {
+        let (a, b) = self.stamp.fork();
+        (
+            GCounter { stamp: a, counts: self.counts.clone() },
+            GCounter { stamp: b, counts: self.counts.clone() },
+        )
+    }
+
+    /// Takes in another replica's increments, keeping this replica's id.
+    pub fn merge(&mut self, other: &GCounter) {
+        self.stamp.event = self.stamp.event.join(&other.stamp.event);
+        merge_counts(&mut self.counts, &other.counts);
+    }
+
+    /// Joins two replicas into one owning both ids, as `Stamp::join` does.
+    pub fn join(&self, other: &GCounter) -> GCounter {
+        let mut counts = self.counts.clone();
+        merge_counts(&mut counts, &other.counts);
+        GCounter { stamp: self.stamp.join(&other.stamp), counts }
+    }
+}
+
+fn merge_counts(into: &mut BTreeMap<IdentPath, u64>, from: &BTreeMap<IdentPath, u64>) {
+    for (region, &count) in from {
+        let mine = into.entry(region.clone()).or_insert(0);
+        *mine = cmp::max(*mine, count);
+    }
+}
@@ -0,0 +1,96 @@
+//! Stable digests of events.
+//!
+//! A digest is the 128-bit FNV-1a hash of the encoding of the normalized event,
+//! so equal events get equal digests on every machine. FNV is not collision
+//! resistant: anyone can craft distinct events with equal digests, so digests
+//! only suit inputs that are not adversarial. Among `n` honest events the
+//! chance of any collision is roughly n² / 2¹²⁹.
+
+use Event;
+use Stamp;
+use compare::leq_by_bounds;
+
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+impl Event {
+    pub fn digest(&self) -> [u8; 16] {
+        fnv1a(&self.norm().encode()).to_be_bytes()
+    }
+}
+
+impl Stamp {
+    /// Key for Bloom filters recording which causal states have been seen.
+    /// It depends only on the event, so stamps with equal events share a key.
+    pub fn bloom_key(&self) -> [u8; 16] {
+        self.event.digest()
+    }
+}
+
+/// Stamps held in normal form together with the digests of their events, for
+/// comparing a probe against many of them. Stamps whose digests differ from
+/// the probe's are unequal to it without looking at their trees, and equal
+/// digests are checked against the trees before being trusted. Digests cannot
+/// tell `leq` apart from concurrency, so the lowest and highest counts of each
+/// event are kept too, which settle `leq` without walking the trees whenever
+/// the probe is entirely below an event or has counts beyond its range.
+#[derive(Clone, Debug, Default)]
+pub struct DigestCache {
+    entries: Vec<Entry>,
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    stamp: Stamp,
+    digest: [u8; 16],
+    bounds: (i64, i64),
+}
+
+impl DigestCache {
+    pub fn new() -> DigestCache {
+        DigestCache { entries: Vec::new() }
+    }
+
+    pub fn insert(&mut self, stamp: &Stamp) {
+        let stamp = stamp.compact();
+        let digest = fnv1a(&stamp.event.encode()).to_be_bytes();
+        let bounds = (stamp.event.min(), stamp.event.max());
+        self.entries.push(Entry { stamp, digest, bounds });
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn stamps(&self) -> impl Iterator<Item = &Stamp> {
+        self.entries.iter().map(|entry| &entry.stamp)
+    }
+
+    /// Whether each cached stamp is equal to `probe`, in insertion order.
+    pub fn eq_each(&self, probe: &Stamp) -> Vec<bool> {
+        let probe = probe.event.norm();
+        let digest = fnv1a(&probe.encode()).to_be_bytes();
+
+        self.entries.iter()
+            .map(|entry| entry.digest == digest && entry.stamp.event.eq_real(&probe))
+            .collect()
+    }
+
+    /// Whether `probe` is `leq` each cached stamp, in insertion order.
+    pub fn leq_each(&self, probe: &Stamp) -> Vec<bool> {
+        let bounds = (probe.event.min(), probe.event.max());
+
+        self.entries.iter()
+            .map(|entry| leq_by_bounds(bounds, entry.bounds)
+                .unwrap_or_else(|| probe.event.leq(&entry.stamp.event)))
+            .collect()
+    }
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> u128 {
+    bytes.iter().fold(FNV_OFFSET, |h, &b| (h ^ b as u128).wrapping_mul(FNV_PRIME))
+}
@@ -0,0 +1,583 @@
+//! Compact binary encoding for ids, events and stamps.
+//!
+//! An event is written in pre-order, one LEB128 varint per tree node. The varint
+//! holds the zigzagged node value shifted left by one bit, with the low bit set
+//! for interior nodes, whose two children follow immediately. An id is written
+//! in pre-order too, one byte per node: 0 for `Zero`, 1 for `One` and 2 for a
+//! `Tuple`, followed by its halves. A stamp is its id followed by its event.
+//!
+//! Decoders refuse trees nested deeper than `MAX_DECODE_DEPTH`, so that hostile
+//! input cannot exhaust the stack. Events are always decoded into normal form,
+//! whether or not the tree they were encoded from was normalized.
+//!
+//! Version 2 of the event encoding, which peers may negotiate through
+//! `EncodingVersion`, shifts the zigzagged value left by two bits instead,
+//! with the low bits 0 for a leaf, 1 for an interior node and 2 for an
+//! interior node whose two children are identical, followed by that child
+//! only. Uniform trees, such as balanced trees of equal leaves, shrink from
+//! exponential to linear in their depth.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::io::{BufRead, Read, Write};
+use std::rc::Rc;
+
+use Event;
+use Ident;
+use ItcError;
+use Stamp;
+use merge_balanced;
+
+/// Nesting deeper than this fails to decode with `DecodeError::TooDeep`.
+pub const MAX_DECODE_DEPTH: usize = 512;
+
+/// Stamps encoding to more bytes than this get advice from `encoding_budget`.
+pub const TYPICAL_STAMP_BYTES: usize = 256;
+
+/// Ways of shrinking an oversized stamp.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BudgetAdvice {
+    /// The event or id is not in normal form; see `Stamp::compact`.
+    Compact,
+    /// The id is fragmented or deep; consider reassigning the id space.
+    Rebalance,
+    /// The event is large even in normal form; see `Event::join_capped`.
+    Prune,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncodingBudget {
+    pub id_bytes: usize,
+    pub event_bytes: usize,
+    pub advice: Vec<BudgetAdvice>,
+}
+
+impl EncodingBudget {
+    pub fn total_bytes(&self) -> usize {
+        self.id_bytes + self.event_bytes
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Truncated,
+    TrailingBytes,
+    TooDeep,
+    Overflow,
+    InvalidTag(u8),
+    UnsupportedVersion(u8),
+    Malformed,
+    Io(io::Error),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DecodeError::Truncated => write!(f, "encoded value is truncated"),
+            DecodeError::TrailingBytes => write!(f, "bytes left over after encoded value"),
+            DecodeError::TooDeep => write!(f, "encoded tree is nested too deeply"),
+            DecodeError::Overflow => write!(f, "encoded number is out of range"),
+            DecodeError::InvalidTag(t) => write!(f, "invalid node tag {}", t),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported encoding version {}", v),
+            DecodeError::Malformed => write!(f, "message does not describe a valid tree"),
+            DecodeError::Io(ref e) => write!(f, "read failed: {}", e),
+        }
+    }
+}
+
+impl error::Error for DecodeError {}
+
+impl From<io::Error> for DecodeError {
+    fn from(e: io::Error) -> DecodeError {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => DecodeError::Truncated,
+            _ => DecodeError::Io(e),
+        }
+    }
+}
+
+/// Versions of the wire format. A versioned encoding is one byte naming the
+/// version, followed by the encoding in that version.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EncodingVersion {
+    /// The format described at the top of this module.
+    V1,
+    /// The format with identical children written once.
+    V2,
+}
+
+impl EncodingVersion {
+    pub fn tag(self) -> u8 {
+        match self {
+            EncodingVersion::V1 => 1,
+            EncodingVersion::V2 => 2,
+        }
+    }
+
+    pub fn from_tag(tag: u8) -> Option<EncodingVersion> {
+        match tag {
+            1 => Some(EncodingVersion::V1),
+            2 => Some(EncodingVersion::V2),
+            _ => None,
+        }
+    }
+
+    /// The newest version both peers support, if any.
+    pub fn negotiate(ours: &[EncodingVersion], theirs: &[EncodingVersion])
+            -> Option<EncodingVersion> {
+        ours.iter().filter(|v| theirs.contains(v)).max().cloned()
+    }
+}
+
+impl Event {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out).expect("writing to a Vec cannot fail");
+        out
+    }
+
+    pub fn encode_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Event::Leaf(n) => write_header(w, n, false),
+            Event::Node(n, ref l, ref r) => {
+                write_header(w, n, true)?;
+                l.encode_to(w)?;
+                r.encode_to(w)
+            }
+        }
+    }
+
+    /// The length of `encode`'s output, without encoding.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            Event::Leaf(n) => varint_len(zigzag(n) << 1),
+            Event::Node(n, ref l, ref r) =>
+                varint_len(zigzag(n) << 1 | 1) + l.encoded_len() + r.encoded_len(),
+        }
+    }
+
+    /// Decodes an event taking up the whole of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Event, DecodeError> {
+        decode_exact(bytes, |r| Event::decode_nested(&mut Slice(r), 0))
+    }
+
+    pub fn decode_from<R: Read>(r: &mut R) -> Result<Event, DecodeError> {
+        Event::decode_nested(&mut Reader(r), 0)
+    }
+
+    fn decode_nested<S: Source>(r: &mut S, depth: usize) -> Result<Event, DecodeError> {
+        let (n, node) = read_header(r)?;
+
+        if !node {
+            return Ok(Event::Leaf(n));
+        }
+
+        if depth >= MAX_DECODE_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+
+        let left = Event::decode_nested(r, depth + 1)?;
+        let right = Event::decode_nested(r, depth + 1)?;
+        normal_node(n, Rc::new(left), Rc::new(right))
+    }
+
+    pub fn encode_versioned(&self, version: EncodingVersion) -> Vec<u8> {
+        let mut out = vec![version.tag()];
+        match version {
+            EncodingVersion::V1 => self.encode_to(&mut out),
+            EncodingVersion::V2 => self.encode_twins(&mut out),
+        }.expect("writing to a Vec cannot fail");
+        out
+    }
+
+    /// Decodes an event written by `encode_versioned`, returning the version
+    /// it was written in.
+    pub fn decode_versioned(bytes: &[u8])
+            -> Result<(Event, EncodingVersion), DecodeError> {
+        let (&tag, rest) = bytes.split_first().ok_or(DecodeError::Truncated)?;
+
+        match EncodingVersion::from_tag(tag) {
+            Some(EncodingVersion::V1) => Ok((Event::decode(rest)?, EncodingVersion::V1)),
+            Some(EncodingVersion::V2) => {
+                let event = decode_exact(rest, |r| Event::decode_twins(&mut Slice(r), 0))?;
+                Ok((event, EncodingVersion::V2))
+            },
+            None => Err(DecodeError::UnsupportedVersion(tag)),
+        }
+    }
+
+    /// Joins every event in a stream of back-to-back encodings, holding only
+    /// the running join in memory. After every `checkpoint_every` events,
+    /// `checkpoint` is called with how many have been joined so far and their
+    /// join, so that a long merge can record its progress.
+    pub fn merge_stream<R, F>(mut r: R, checkpoint_every: usize, mut checkpoint: F)
+            -> Result<Event, DecodeError>
+            where R: BufRead, F: FnMut(u64, &Event) {
+        let mut joined = Event::seed();
+        let mut count = 0u64;
+
+        while !r.fill_buf()?.is_empty() {
+            joined = joined.join(&Event::decode_from(&mut r)?);
+            count += 1;
+
+            if checkpoint_every > 0 && count.is_multiple_of(checkpoint_every as u64) {
+                checkpoint(count, &joined);
+            }
+        }
+
+        Ok(joined)
+    }
+
+    fn encode_twins<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Event::Leaf(n) => write_varint(w, zigzag(n) << 2),
+            Event::Node(n, ref l, ref r) if Rc::ptr_eq(l, r) || l.eq_real(r) => {
+                write_varint(w, zigzag(n) << 2 | 2)?;
+                l.encode_twins(w)
+            },
+            Event::Node(n, ref l, ref r) => {
+                write_varint(w, zigzag(n) << 2 | 1)?;
+                l.encode_twins(w)?;
+                r.encode_twins(w)
+            },
+        }
+    }
+
+    fn decode_twins<S: Source>(r: &mut S, depth: usize) -> Result<Event, DecodeError> {
+        let v = read_varint(r, 66)?;
+        let n = unzigzag((v >> 2) as u64);
+        let kind = v & 3;
+
+        if kind == 0 {
+            return Ok(Event::Leaf(n));
+        }
+
+        if depth >= MAX_DECODE_DEPTH {
+            return Err(DecodeError::TooDeep);
+        }
+
+        match kind {
+            1 => {
+                let left = Event::decode_twins(r, depth + 1)?;
+                let right = Event::decode_twins(r, depth + 1)?;
+                normal_node(n, Rc::new(left), Rc::new(right))
+            },
+            2 => {
+                let child = Rc::new(Event::decode_twins(r, depth + 1)?);
+                normal_node(n, child.clone(), child)
+            },
+            _ => Err(DecodeError::InvalidTag(kind as u8)),
+        }
+    }
+
+    /// Whether `bytes` is exactly the canonical encoding of an event: a tree in
+    /// normal form, written with the shortest varints, with nothing after it.
+    pub fn is_minimal_encoding(bytes: &[u8]) -> bool {
+        match Event::decode(bytes) {
+            Ok(e) => e.norm().encode() == bytes,
+            Err(_) => false,
+        }
+    }
+
+    /// Decodes and joins a batch of encoded events, as `merge_all` would,
+    /// without first collecting the decoded events.
+    pub fn join_encoded(blobs: &[&[u8]]) -> Result<Event, ItcError> {
+        merge_balanced(blobs.iter().map(|b| Event::decode(b).map_err(ItcError::from)))
+    }
+}
+
+impl Ident {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out).expect("writing to a Vec cannot fail");
+        out
+    }
+
+    pub fn encode_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        match *self {
+            Ident::Zero => w.write_all(&[0]),
+            Ident::One => w.write_all(&[1]),
+            Ident::Tuple(ref l, ref r) => {
+                w.write_all(&[2])?;
+                l.encode_to(w)?;
+                r.encode_to(w)
+            }
+        }
+    }
+
+    /// The length of `encode`'s output, one byte per node.
+    pub fn encoded_len(&self) -> usize {
+        match *self {
+            Ident::Zero | Ident::One => 1,
+            Ident::Tuple(ref l, ref r) => 1 + l.encoded_len() + r.encoded_len(),
+        }
+    }
+
+    /// Decodes an id taking up the whole of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Ident, DecodeError> {
+        decode_exact(bytes, |r| Ident::decode_nested(&mut Slice(r), 0))
+    }
+
+    pub fn decode_from<R: Read>(r: &mut R) -> Result<Ident, DecodeError> {
+        Ident::decode_nested(&mut Reader(r), 0)
+    }
+
+    fn decode_nested<S: Source>(r: &mut S, depth: usize) -> Result<Ident, DecodeError> {
+        match r.byte()? {
+            0 => Ok(Ident::Zero),
+            1 => Ok(Ident::One),
+            2 if depth >= MAX_DECODE_DEPTH => Err(DecodeError::TooDeep),
+            2 => {
+                let left = Ident::decode_nested(r, depth + 1)?;
+                let right = Ident::decode_nested(r, depth + 1)?;
+                Ok(Ident::Tuple(Rc::new(left), Rc::new(right)))
+            },
+            t => Err(DecodeError::InvalidTag(t)),
+        }
+    }
+}
+
+impl Stamp {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_to(&mut out).expect("writing to a Vec cannot fail");
+        out
+    }
+
+    pub fn encode_to<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        self.id.encode_to(w)?;
+        self.event.encode_to(w)
+    }
+
+    /// Breaks down the stamp's encoded size, with advice on shrinking it
+    /// once it outgrows `TYPICAL_STAMP_BYTES`.
+    pub fn encoding_budget(&self) -> EncodingBudget {
+        let id_bytes = self.id.encoded_len();
+        let event_bytes = self.event.encoded_len();
+        let mut advice = Vec::new();
+
+        if id_bytes + event_bytes > TYPICAL_STAMP_BYTES {
+            let normal = self.event.norm();
+            let compact_bytes = normal.encoded_len();
+
+            if compact_bytes < event_bytes || self.id.canonical().encoded_len() < id_bytes {
+                advice.push(BudgetAdvice::Compact);
+            }
+            if id_bytes > TYPICAL_STAMP_BYTES / 4 {
+                advice.push(BudgetAdvice::Rebalance);
+            }
+            if compact_bytes > TYPICAL_STAMP_BYTES / 2 {
+                advice.push(BudgetAdvice::Prune);
+            }
+        }
+
+        EncodingBudget { id_bytes, event_bytes, advice }
+    }
+
+    /// Decodes a stamp taking up the whole of `bytes`.
+    pub fn decode(bytes: &[u8]) -> Result<Stamp, DecodeError> {
+        decode_exact(bytes, |r| Stamp::decode_nested(&mut Slice(r)))
+    }
+
+    pub fn decode_from<R: Read>(r: &mut R) -> Result<Stamp, DecodeError> {
+        Stamp::decode_nested(&mut Reader(r))
+    }
+
+    fn decode_nested<S: Source>(r: &mut S) -> Result<Stamp, DecodeError> {
+        let id = Ident::decode_nested(r, 0)?;
+        let event = Event::decode_nested(r, 0)?;
+        Ok(Stamp::new(id, event))
+    }
+}
+
+// Where decoders take their bytes from. Slices get their own source so that
+// decoding from memory reads bytes directly instead of through `Read`.
+trait Source {
+    fn byte(&mut self) -> Result<u8, DecodeError>;
+}
+
+struct Reader<'r, R: 'r>(&'r mut R);
+
+impl<'r, R: Read> Source for Reader<'r, R> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let mut byte = [0u8];
+        self.0.read_exact(&mut byte)?;
+        Ok(byte[0])
+    }
+}
+
+struct Slice<'r, 'a: 'r>(&'r mut &'a [u8]);
+
+impl<'r, 'a> Source for Slice<'r, 'a> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        match self.0.split_first() {
+            Some((&byte, rest)) => {
+                *self.0 = rest;
+                Ok(byte)
+            },
+            None => Err(DecodeError::Truncated),
+        }
+    }
+}
+
+// Builds a decoded node in normal form from children that already are, as
+// `Event::node` does, keeping twin children shared.
+pub(crate) fn normal_node(n: i64, left: Rc<Event>, right: Rc<Event>) -> Result<Event, DecodeError> {
+    if let (Event::Leaf(m1), Event::Leaf(m2)) = (&*left, &*right) {
+        if m1 == m2 {
+            return n.checked_add(*m1).map(Event::Leaf).ok_or(DecodeError::Overflow);
+        }
+    }
+
+    let m = std::cmp::min(left.value(), right.value());
+
+    if m == 0 {
+        return Ok(Event::Node(n, left, right));
+    }
+
+    let top = n.checked_add(m).ok_or(DecodeError::Overflow)?;
+    left.value().checked_sub(m)
+        .and(right.value().checked_sub(m))
+        .ok_or(DecodeError::Overflow)?;
+
+    let sunk = Rc::new((*left).clone().sink(m));
+
+    if Rc::ptr_eq(&left, &right) {
+        Ok(Event::Node(top, sunk.clone(), sunk))
+    } else {
+        Ok(Event::Node(top, sunk, Rc::new((*right).clone().sink(m))))
+    }
+}
+
+fn decode_exact<'a, T, F>(bytes: &'a [u8], decode: F) -> Result<T, DecodeError>
+        where F: FnOnce(&mut &'a [u8]) -> Result<T, DecodeError> {
+    let mut r = bytes;
+    let value = decode(&mut r)?;
+
+    if !r.is_empty() {
+        return Err(DecodeError::TrailingBytes);
+    }
+
+    Ok(value)
+}
+
+/// Compares two encoded events with `Event::leq` semantics, decoding both in
+/// lockstep and stopping at the first region where `a` exceeds `b`. Only the
+/// current path through each tree is held in memory.
+pub fn leq_streaming<R1: Read, R2: Read>(mut a: R1, mut b: R2)
+        -> Result<bool, DecodeError> {
+    leq_subtrees(Some(&mut a), 0, Some(&mut b), 0, 0)
+}
+
+// Each side is either a reader positioned at a subtree, or `None` when that
+// side is a leaf already read and stands for a constant over the region.
+fn leq_subtrees<R1: Read, R2: Read>(a: Option<&mut R1>, oa: i64,
+                                    b: Option<&mut R2>, ob: i64,
+                                    depth: usize)
+        -> Result<bool, DecodeError> {
+    let (va, a) = match a {
+        Some(r) => {
+            let (n, node) = read_header(&mut Reader(r))?;
+            let v = oa.checked_add(n).ok_or(DecodeError::Overflow)?;
+            (v, if node { Some(r) } else { None })
+        },
+        None => (oa, None),
+    };
+
+    let (vb, b) = match b {
+        Some(r) => {
+            let (n, node) = read_header(&mut Reader(r))?;
+            let v = ob.checked_add(n).ok_or(DecodeError::Overflow)?;
+            (v, if node { Some(r) } else { None })
+        },
+        None => (ob, None),
+    };
+
+    match (a, b) {
+        (None, None) => Ok(va <= vb),
+
+        _ if depth >= MAX_DECODE_DEPTH => Err(DecodeError::TooDeep),
+
+        (mut a, mut b) => {
+            let left = leq_subtrees(
+                a.as_deref_mut(), va,
+                b.as_deref_mut(), vb,
+                depth + 1
+            )?;
+
+            if !left {
+                return Ok(false);
+            }
+
+            leq_subtrees(a, va, b, vb, depth + 1)
+        },
+    }
+}
+
+fn write_header<W: Write>(w: &mut W, n: i64, node: bool) -> io::Result<()> {
+    write_varint(w, zigzag(n) << 1 | node as u128)
+}
+
+fn read_header<S: Source>(r: &mut S) -> Result<(i64, bool), DecodeError> {
+    let v = read_varint(r, 65)?;
+    Ok((unzigzag((v >> 1) as u64), v & 1 == 1))
+}
+
+fn zigzag(n: i64) -> u128 {
+    ((n << 1) ^ (n >> 63)) as u64 as u128
+}
+
+fn unzigzag(z: u64) -> i64 {
+    ((z >> 1) as i64) ^ -((z & 1) as i64)
+}
+
+fn varint_len(v: u128) -> usize {
+    std::cmp::max(128 - v.leading_zeros() as usize, 1).div_ceil(7)
+}
+
+fn write_varint<W: Write>(w: &mut W, mut v: u128) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+
+        if v == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+
+    w.write_all(&buf[..len])
+}
+
+// Reads a varint of at most `max_bits` bits, between 64 and 70, so that it
+// fits in ten bytes.
+fn read_varint<S: Source>(r: &mut S, max_bits: u32) -> Result<u128, DecodeError> {
+    let mut v: u128 = 0;
+    let mut shift = 0;
+
+    loop {
+        let byte = r.byte()?;
+
+        let bits = (byte & 0x7f) as u128;
+        if shift > 63 || (shift == 63 && bits >> (max_bits - 63) != 0) {
+            return Err(DecodeError::Overflow);
+        }
+
+        v |= bits << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    Ok(v)
+}
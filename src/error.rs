@@ -0,0 +1,49 @@
+use std::error;
+use std::fmt;
+
+use DecodeError;
+
+#[derive(Debug)]
+pub enum ItcError {
+    Decode(DecodeError),
+    NotDominating,
+    Concurrent,
+    Overflow,
+    Overlap,
+    LimitExceeded,
+    Monotonicity,
+    DuplicateNode(u128),
+    UnknownNode(u128),
+}
+
+impl fmt::Display for ItcError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ItcError::Decode(ref e) => write!(f, "decode failed: {}", e),
+            ItcError::NotDominating =>
+                write!(f, "event does not dominate the base it is compared with"),
+            ItcError::Concurrent => write!(f, "stamps are concurrent"),
+            ItcError::Overflow => write!(f, "event value out of range"),
+            ItcError::Overlap => write!(f, "ids overlap"),
+            ItcError::LimitExceeded => write!(f, "stamp would exceed its size limits"),
+            ItcError::Monotonicity => write!(f, "update would lower the event"),
+            ItcError::DuplicateNode(n) => write!(f, "node {:032x} is already registered", n),
+            ItcError::UnknownNode(n) => write!(f, "node {:032x} is not registered", n),
+        }
+    }
+}
+
+impl error::Error for ItcError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ItcError::Decode(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<DecodeError> for ItcError {
+    fn from(e: DecodeError) -> ItcError {
+        ItcError::Decode(e)
+    }
+}
@@ -0,0 +1,171 @@
+//! Borrowed views of ids and events for C callers.
+//!
+//! An `EventRef` or `IdentRef` is a handle onto a node of a tree owned by Rust
+//! code. C code walks the tree by asking for a node's children, which are
+//! handles too, and reads values without ever taking ownership or copying.
+//! Handles are only valid while the tree they point into is alive. Walks
+//! start from a stamp the embedding code hands to C, through `itc_stamp_id`
+//! and `itc_stamp_event`.
+
+use std::marker::PhantomData;
+use std::ptr;
+
+use Event;
+use Ident;
+use Stamp;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct EventRef<'a> {
+    node: *const Event,
+    tree: PhantomData<&'a Event>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct IdentRef<'a> {
+    node: *const Ident,
+    tree: PhantomData<&'a Ident>,
+}
+
+/// Id node kinds, as returned by `itc_ident_kind`.
+pub const ITC_IDENT_ZERO: u8 = 0;
+pub const ITC_IDENT_ONE: u8 = 1;
+pub const ITC_IDENT_TUPLE: u8 = 2;
+
+impl<'a> EventRef<'a> {
+    pub fn new(event: &'a Event) -> EventRef<'a> {
+        EventRef { node: event, tree: PhantomData }
+    }
+
+    fn null() -> EventRef<'a> {
+        EventRef { node: ptr::null(), tree: PhantomData }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.node.is_null()
+    }
+}
+
+impl<'a> IdentRef<'a> {
+    pub fn new(id: &'a Ident) -> IdentRef<'a> {
+        IdentRef { node: id, tree: PhantomData }
+    }
+
+    fn null() -> IdentRef<'a> {
+        IdentRef { node: ptr::null(), tree: PhantomData }
+    }
+
+    pub fn is_null(&self) -> bool {
+        self.node.is_null()
+    }
+}
+
+/// A handle onto the root of the stamp's id.
+///
+/// # Safety
+///
+/// `s` must point to a live stamp, which must outlive the handle.
+#[no_mangle]
+pub unsafe extern "C" fn itc_stamp_id<'a>(s: *const Stamp) -> IdentRef<'a> {
+    IdentRef::new(&(*s).id)
+}
+
+/// A handle onto the root of the stamp's event.
+///
+/// # Safety
+///
+/// `s` must point to a live stamp, which must outlive the handle.
+#[no_mangle]
+pub unsafe extern "C" fn itc_stamp_event<'a>(s: *const Stamp) -> EventRef<'a> {
+    EventRef::new(&(*s).event)
+}
+
+/// Whether the node is a leaf.
+///
+/// # Safety
+///
+/// `e` must be a non-null handle into a live event.
+#[no_mangle]
+pub unsafe extern "C" fn itc_event_is_leaf(e: EventRef) -> bool {
+    match *e.node {
+        Event::Leaf(_) => true,
+        Event::Node(..) => false,
+    }
+}
+
+/// The node's value, relative to its parent.
+///
+/// # Safety
+///
+/// `e` must be a non-null handle into a live event.
+#[no_mangle]
+pub unsafe extern "C" fn itc_event_value(e: EventRef) -> i64 {
+    (*e.node).value()
+}
+
+/// The node's left child, or a null handle for a leaf.
+///
+/// # Safety
+///
+/// `e` must be a non-null handle into a live event.
+#[no_mangle]
+pub unsafe extern "C" fn itc_event_left(e: EventRef) -> EventRef {
+    match *e.node {
+        Event::Leaf(_) => EventRef::null(),
+        Event::Node(_, ref l, _) => EventRef::new(l),
+    }
+}
+
+/// The node's right child, or a null handle for a leaf.
+///
+/// # Safety
+///
+/// `e` must be a non-null handle into a live event.
+#[no_mangle]
+pub unsafe extern "C" fn itc_event_right(e: EventRef) -> EventRef {
+    match *e.node {
+        Event::Leaf(_) => EventRef::null(),
+        Event::Node(_, _, ref r) => EventRef::new(r),
+    }
+}
+
+/// One of the `ITC_IDENT_*` kinds.
+///
+/// # Safety
+///
+/// `i` must be a non-null handle into a live id.
+#[no_mangle]
+pub unsafe extern "C" fn itc_ident_kind(i: IdentRef) -> u8 {
+    match *i.node {
+        Ident::Zero => ITC_IDENT_ZERO,
+        Ident::One => ITC_IDENT_ONE,
+        Ident::Tuple(..) => ITC_IDENT_TUPLE,
+    }
+}
+
+/// The left half of a `Tuple`, or a null handle otherwise.
+///
+/// # Safety
+///
+/// `i` must be a non-null handle into a live id.
+#[no_mangle]
+pub unsafe extern "C" fn itc_ident_left(i: IdentRef) -> IdentRef {
+    match *i.node {
+        Ident::Tuple(ref l, _) => IdentRef::new(l),
+        _ => IdentRef::null(),
+    }
+}
+
+/// The right half of a `Tuple`, or a null handle otherwise.
+///
+/// # Safety
+///
+/// `i` must be a non-null handle into a live id.
+#[no_mangle]
+pub unsafe extern "C" fn itc_ident_right(i: IdentRef) -> IdentRef {
+    match *i.node {
+        Ident::Tuple(_, ref r) => IdentRef::new(r),
+        _ => IdentRef::null(),
+    }
+}
@@ -0,0 +1,163 @@
+//! Events as flat arrays, for bulk arithmetic over many clocks.
+//!
+//! A `FlatEvent` lists the tree's nodes in breadth-first order. Bit `i` of the
+//! shape bitmap, counting from the low bit of word `i / 64`, is set when node
+//! `i` is interior; the children of the `k`-th interior node are nodes
+//! `2k + 1` and `2k + 2`. Each node's entry in `values` is its absolute value,
+//! including all of its ancestors' values, so the events of a batch of clocks
+//! with the same shape can be compared leaf by leaf.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use DecodeError;
+use Event;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FlatEvent {
+    shape: Vec<u64>,
+    values: Vec<i64>,
+}
+
+impl FlatEvent {
+    /// Checks that `shape` and `values` describe a tree as laid out above.
+    pub fn new(shape: Vec<u64>, values: Vec<i64>) -> Result<FlatEvent, DecodeError> {
+        let flat = FlatEvent { shape, values };
+        let mut interior = 0;
+
+        // Every node but the root must be the child of an earlier one.
+        for i in 0..flat.values.len() {
+            if i > 2 * interior {
+                return Err(DecodeError::Malformed);
+            }
+            if flat.is_interior(i) {
+                interior += 1;
+            }
+        }
+
+        let bits = flat.shape.iter().map(|w| w.count_ones() as usize).sum::<usize>();
+        if flat.values.len() != 2 * interior + 1 || bits != interior {
+            return Err(DecodeError::Malformed);
+        }
+
+        Ok(flat)
+    }
+
+    pub fn shape(&self) -> &[u64] {
+        &self.shape
+    }
+
+    pub fn values(&self) -> &[i64] {
+        &self.values
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn is_interior(&self, i: usize) -> bool {
+        self.shape.get(i / 64).is_some_and(|w| w >> (i % 64) & 1 == 1)
+    }
+
+    pub fn same_shape(&self, other: &FlatEvent) -> bool {
+        self.values.len() == other.values.len() && self.shape == other.shape
+    }
+
+    /// Compares two events of the same shape leaf by leaf, as `Event` does
+    /// in general. `None` if the shapes differ.
+    pub fn leq(&self, other: &FlatEvent) -> Option<bool> {
+        if !self.same_shape(other) {
+            return None;
+        }
+
+        Some(self.leaves().zip(other.leaves()).all(|(a, b)| a <= b))
+    }
+
+    /// Like `leq`, giving the causal order, or `None` when the events are
+    /// concurrent or differ in shape.
+    pub fn causal_cmp(&self, other: &FlatEvent) -> Option<Ordering> {
+        match (self.leq(other)?, other.leq(self)?) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+
+    pub fn min(&self) -> i64 {
+        self.leaves().min().unwrap_or(0)
+    }
+
+    pub fn max(&self) -> i64 {
+        self.leaves().max().unwrap_or(0)
+    }
+
+    fn leaves(&self) -> impl Iterator<Item = i64> + '_ {
+        self.values.iter().enumerate()
+            .filter(move |&(i, _)| !self.is_interior(i))
+            .map(|(_, &v)| v)
+    }
+}
+
+impl Event {
+    pub fn to_flat(&self) -> FlatEvent {
+        let mut shape = Vec::new();
+        let mut values = Vec::new();
+        let mut level = vec![(self, 0)];
+
+        while !level.is_empty() {
+            let mut next = Vec::new();
+
+            for (e, base) in level {
+                let i = values.len();
+                if shape.len() <= i / 64 {
+                    shape.push(0);
+                }
+
+                match *e {
+                    Event::Leaf(n) => values.push(base + n),
+                    Event::Node(n, ref l, ref r) => {
+                        shape[i / 64] |= 1 << (i % 64);
+                        values.push(base + n);
+                        next.push((&**l, base + n));
+                        next.push((&**r, base + n));
+                    },
+                }
+            }
+
+            level = next;
+        }
+
+        FlatEvent { shape, values }
+    }
+
+    pub fn from_flat(flat: &FlatEvent) -> Event {
+        let interior: Vec<usize> = (0..flat.len()).filter(|&i| flat.is_interior(i)).collect();
+        let mut built: Vec<Option<Rc<Event>>> = vec![None; flat.len()];
+
+        // Build from the last node back, so that children exist before their
+        // parents.
+        for (k, i) in (0..flat.len()).rev().map(|i| (interior.binary_search(&i), i)) {
+            let parent = if i == 0 { 0 } else { flat.values[interior[(i - 1) / 2]] };
+            let n = flat.values[i] - parent;
+
+            built[i] = Some(Rc::new(match k {
+                Ok(k) => Event::Node(n, take(&mut built, 2 * k + 1), take(&mut built, 2 * k + 2)),
+                Err(_) => Event::Leaf(n),
+            }));
+        }
+
+        match built.into_iter().next() {
+            Some(Some(root)) => (*root).clone(),
+            _ => Event::seed(),
+        }
+    }
+}
+
+fn take(built: &mut [Option<Rc<Event>>], i: usize) -> Rc<Event> {
+    built[i].take().expect("children are built before their parents")
+}
@@ -0,0 +1,53 @@
+//! `Arbitrary` impls for property tests and fuzzing.
+//!
+//! Generated trees are capped at `MAX_DEPTH` levels, and event values are kept
+//! small enough that no sequence of operations a test is likely to run can
+//! overflow.
+
+use std::rc::Rc;
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use Event;
+use Ident;
+use Stamp;
+
+const MAX_DEPTH: usize = 12;
+
+impl<'a> Arbitrary<'a> for Ident {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Ident> {
+        ident(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Event {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Event> {
+        event(u, 0)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Stamp {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Stamp> {
+        Ok(Stamp::new(ident(u, 0)?, event(u, 0)?))
+    }
+}
+
+fn ident(u: &mut Unstructured, depth: usize) -> Result<Ident> {
+    let tag = if depth >= MAX_DEPTH { u.int_in_range(0..=1)? } else { u.int_in_range(0..=2)? };
+
+    match tag {
+        0 => Ok(Ident::Zero),
+        1 => Ok(Ident::One),
+        _ => Ok(Ident::Tuple(Rc::new(ident(u, depth + 1)?), Rc::new(ident(u, depth + 1)?))),
+    }
+}
+
+fn event(u: &mut Unstructured, depth: usize) -> Result<Event> {
+    let n = u.int_in_range(0..=1 << 16)?;
+
+    if depth >= MAX_DEPTH || !u.arbitrary::<bool>()? {
+        return Ok(Event::Leaf(n));
+    }
+
+    Ok(Event::Node(n, Rc::new(event(u, depth + 1)?), Rc::new(event(u, depth + 1)?)))
+}
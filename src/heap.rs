@@ -0,0 +1,78 @@
+//! A priority queue of stamps in causal order.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use CausalTimestamp;
+use Stamp;
+
+/// Pops stamps in `total_cmp` order, least first, so that a stamp is never
+/// popped before one that happened before it. Concurrent stamps come out in
+/// the order `total_cmp` puts them, and stamps with equal events in the order
+/// they were pushed.
+pub struct StampHeap<T> {
+    heap: BinaryHeap<Entry<T>>,
+    pushed: u64,
+}
+
+struct Entry<T> {
+    key: CausalTimestamp,
+    seq: u64,
+    stamp: Stamp,
+    value: T,
+}
+
+impl<T> StampHeap<T> {
+    pub fn new() -> StampHeap<T> {
+        StampHeap { heap: BinaryHeap::new(), pushed: 0 }
+    }
+
+    pub fn push(&mut self, stamp: Stamp, value: T) {
+        let key = stamp.as_causal_timestamp();
+        self.heap.push(Entry { key, seq: self.pushed, stamp, value });
+        self.pushed += 1;
+    }
+
+    pub fn pop(&mut self) -> Option<(Stamp, T)> {
+        self.heap.pop().map(|e| (e.stamp, e.value))
+    }
+
+    pub fn peek(&self) -> Option<(&Stamp, &T)> {
+        self.heap.peek().map(|e| (&e.stamp, &e.value))
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+}
+
+impl<T> Default for StampHeap<T> {
+    fn default() -> StampHeap<T> {
+        StampHeap::new()
+    }
+}
+
+// `BinaryHeap` pops its greatest entry, so entries order backwards.
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Entry<T>) -> Ordering {
+        other.key.cmp(&self.key).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Entry<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Entry<T>) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T> Eq for Entry<T> {}
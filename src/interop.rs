@@ -0,0 +1,48 @@
+//! Conversions to and from other ITC implementations.
+//!
+//! Conversions go through the canonical wire encoding, so they work with any
+//! implementation able to read and write it, whatever its representation. A
+//! foreign type opts in by implementing `ForeignEvent` or `ForeignIdent`.
+
+use DecodeError;
+use Event;
+use Ident;
+
+/// An event type from another implementation that speaks the canonical
+/// encoding.
+pub trait ForeignEvent: Sized {
+    type Error;
+
+    fn to_canonical_bytes(&self) -> Vec<u8>;
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Self::Error>;
+}
+
+/// An id type from another implementation that speaks the canonical encoding.
+pub trait ForeignIdent: Sized {
+    type Error;
+
+    fn to_canonical_bytes(&self) -> Vec<u8>;
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Self, Self::Error>;
+}
+
+impl Event {
+    pub fn from_foreign<T: ForeignEvent>(e: &T) -> Result<Event, DecodeError> {
+        Event::decode(&e.to_canonical_bytes())
+    }
+
+    pub fn to_foreign<T: ForeignEvent>(&self) -> Result<T, T::Error> {
+        T::from_canonical_bytes(&self.encode())
+    }
+}
+
+impl Ident {
+    pub fn from_foreign<T: ForeignIdent>(id: &T) -> Result<Ident, DecodeError> {
+        Ident::decode(&id.to_canonical_bytes())
+    }
+
+    pub fn to_foreign<T: ForeignIdent>(&self) -> Result<T, T::Error> {
+        T::from_canonical_bytes(&self.encode())
+    }
+}
@@ -0,0 +1,99 @@
+//! Ids leased to ephemeral nodes.
+//!
+//! A `LeasedIdPool` hands out slices of the id space it holds for a limited
+//! time. A holder that stops renewing its lease, say because it crashed, has
+//! its id swept back into the pool rather than leaving a permanent hole.
+//!
+//! Events the old holder made under a reclaimed id still exist wherever they
+//! were seen, so the next holder of that id must start from a stamp that has
+//! seen them too, for example by joining the cluster's latest event before
+//! ticking.
+
+use std::time::{Duration, Instant};
+
+use Ident;
+
+pub struct LeasedIdPool {
+    free: Ident,
+    leases: Vec<(Ident, Instant)>,
+}
+
+impl LeasedIdPool {
+    /// Creates a pool handing out slices of `id`.
+    pub fn new(id: Ident) -> LeasedIdPool {
+        LeasedIdPool {
+            free: id.canonical(),
+            leases: Vec::new(),
+        }
+    }
+
+    /// Leases half of the free id space for `ttl`, or `None` if the pool has
+    /// nothing left to give.
+    pub fn acquire_lease(&mut self, ttl: Duration) -> Option<Ident> {
+        self.acquire_lease_at(Instant::now(), ttl)
+    }
+
+    pub fn acquire_lease_at(&mut self, now: Instant, ttl: Duration) -> Option<Ident> {
+        if self.free == Ident::Zero {
+            return None;
+        }
+
+        let (kept, given) = self.free.split();
+        self.free = kept;
+        self.leases.push((given.clone(), now + ttl));
+        Some(given)
+    }
+
+    /// Extends the lease on `id` to `ttl` from now. Returns false if `id` is
+    /// not leased, or its lease has already been swept.
+    pub fn renew(&mut self, id: &Ident, ttl: Duration) -> bool {
+        self.renew_at(Instant::now(), id, ttl)
+    }
+
+    pub fn renew_at(&mut self, now: Instant, id: &Ident, ttl: Duration) -> bool {
+        match self.leases.iter_mut().find(|(leased, _)| leased == id) {
+            Some(lease) => {
+                lease.1 = now + ttl;
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Hands a lease back before it expires. Returns false if `id` is not
+    /// leased.
+    pub fn release(&mut self, id: &Ident) -> bool {
+        match self.leases.iter().position(|(leased, _)| leased == id) {
+            Some(i) => {
+                let (id, _) = self.leases.swap_remove(i);
+                self.free = self.free.sum(&id).canonical();
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// Reclaims every lease that has expired, returning the reclaimed ids.
+    pub fn sweep_expired(&mut self) -> Vec<Ident> {
+        self.sweep_expired_at(Instant::now())
+    }
+
+    pub fn sweep_expired_at(&mut self, now: Instant) -> Vec<Ident> {
+        let (expired, live) = self.leases.drain(..).partition(|&(_, until)| until <= now);
+        self.leases = live;
+
+        expired.into_iter().map(|(id, _)| {
+            self.free = self.free.sum(&id).canonical();
+            id
+        }).collect()
+    }
+
+    /// The part of the id space not currently leased.
+    pub fn free(&self) -> &Ident {
+        &self.free
+    }
+
+    pub fn leased(&self) -> usize {
+        self.leases.len()
+    }
+}
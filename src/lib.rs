@@ -3,10 +3,82 @@
 //! frequent cluster membership changes.
 
 use std::cmp::Ord;
+use std::collections::HashMap;
 use std::cmp::Ordering;
+use std::convert::Infallible;
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
 use std::rc::Rc;
 
-#[derive(Clone)]
+mod audit;
+mod compare;
+mod counter;
+mod digest;
+mod encoding;
+mod error;
+mod flat;
+mod heap;
+mod lease;
+mod limits;
+mod log;
+mod map;
+mod path;
+mod registry;
+mod sparse;
+mod stamp;
+mod structural;
+mod tracked;
+
+pub use audit::{FairnessReport, ForkOp, IdSpaceReport, fork_fairness, id_space_report};
+pub use compare::StampComparator;
+pub use counter::GCounter;
+pub use digest::DigestCache;
+pub use encoding::{BudgetAdvice, DecodeError, EncodingBudget, EncodingVersion, MAX_DECODE_DEPTH,
+                   TYPICAL_STAMP_BYTES, leq_streaming};
+pub use error::ItcError;
+pub use flat::FlatEvent;
+pub use heap::StampHeap;
+pub use lease::LeasedIdPool;
+pub use limits::Limits;
+pub use log::{LogOp, StampLog, compact_log};
+pub use map::{InsertOutcome, StampMap};
+pub use path::IdentPath;
+pub use registry::{NodeRegistry, NodeStamp};
+pub use sparse::SparseEvent;
+pub use stamp::{CausalTimestamp, CompactPolicy, Deliveries, LabeledStamp, Stamp};
+pub use structural::Structural;
+pub use tracked::TrackedEvent;
+
+#[cfg(feature = "arbitrary")]
+extern crate arbitrary;
+#[cfg(feature = "tokio")]
+extern crate bytes;
+#[cfg(feature = "protobuf")]
+extern crate prost;
+#[cfg(feature = "lineage")]
+extern crate sha2;
+#[cfg(feature = "tokio")]
+extern crate tokio_util;
+
+#[cfg(feature = "tokio")]
+pub mod codec;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(feature = "arbitrary")]
+mod generate;
+
+#[cfg(feature = "interop")]
+pub mod interop;
+
+#[cfg(feature = "lineage")]
+pub mod lineage;
+
+#[cfg(feature = "protobuf")]
+pub mod proto;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Ident {
     Zero,
     One,
@@ -36,8 +108,10 @@ impl Ident {
                 )
             },
 
+            // Children are tested for zero up to normalization, so that ids
+            // equal up to normalization split alike.
             Tuple(ref i1, ref i2) => match (&**i1, &**i2) {
-                (&Zero, ref id) => {
+                (z, id) if z.is_zero() => {
                     let (l, r) = id.split();
                     (
                         Tuple(i1.clone(), Rc::new(l)),
@@ -45,7 +119,7 @@ impl Ident {
                     )
                 },
 
-                (ref id, &Zero) => {
+                (id, z) if z.is_zero() => {
                     let (l, r) = id.split();
                     (
                         Tuple(Rc::new(l), i2.clone()),
@@ -63,6 +137,54 @@ impl Ident {
         }
     }
 
+    /// Splits the id as `split` does, also returning the depth of the deeper
+    /// half so that callers can watch the id tree grow.
+    pub fn split_reporting(&self) -> ((Ident, Ident), u32) {
+        let (l, r) = self.split();
+        let depth = std::cmp::max(l.depth(), r.depth()) as u32;
+        ((l, r), depth)
+    }
+
+    /// Splits off a small share for a child, keeping the rest: the child gets
+    /// what `split` would give it after `reserve_for_self` further splits, and
+    /// every piece split away from it along the way stays with `self`.
+    pub fn reserve_split(&self, reserve_for_self: u32) -> (Ident, Ident) {
+        let (mut kept, mut given) = self.split();
+
+        for _ in 0..reserve_for_self {
+            let (l, r) = given.split();
+            kept = kept.sum(&l);
+            given = r;
+        }
+
+        (kept, given)
+    }
+
+    /// How many disjoint ids this one could be forked into without any of
+    /// them being deeper than `max_depth`: the number of slots it fully owns
+    /// when the id space is cut into `2^max_depth` of them, saturating.
+    pub fn fork_capacity(&self, max_depth: usize) -> u64 {
+        use Ident::*;
+
+        match *self {
+            Zero => 0,
+            One => if max_depth >= 64 { u64::MAX } else { 1 << max_depth },
+            Tuple(_, _) if max_depth == 0 => self.is_full() as u64,
+            Tuple(ref l, ref r) =>
+                l.fork_capacity(max_depth - 1)
+                    .saturating_add(r.fork_capacity(max_depth - 1)),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        use Ident::*;
+
+        match *self {
+            Zero | One => 0,
+            Tuple(ref l, ref r) => 1 + std::cmp::max(l.depth(), r.depth()),
+        }
+    }
+
     pub fn norm(self) -> Ident {
         use Ident::*;
 
@@ -77,6 +199,98 @@ impl Ident {
         }
     }
 
+    /// Whether this id owns the whole of slot `index` when the id space is cut
+    /// into `total_leaves` equal slots, rounded up to a power of two.
+    pub fn owns_leaf(&self, total_leaves: usize, index: usize) -> bool {
+        use Ident::*;
+
+        if index >= total_leaves {
+            return false;
+        }
+
+        let depth = total_leaves.next_power_of_two().trailing_zeros();
+        let mut id = self;
+
+        for level in (0..depth).rev() {
+            match *id {
+                Zero => return false,
+                One => return true,
+                Tuple(ref l, ref r) =>
+                    id = if (index >> level) & 1 == 0 { l } else { r },
+            }
+        }
+
+        id.is_full()
+    }
+
+    /// The smallest id owning every region any of `ids` owns, in canonical
+    /// form. Unlike `sum`, overlapping ids are fine.
+    pub fn least_upper_id(ids: &[Ident]) -> Ident {
+        ids.iter().fold(Ident::Zero, |all, id| all.union(id)).canonical()
+    }
+
+    fn union(&self, other: &Ident) -> Ident {
+        use Ident::*;
+
+        match (self, other) {
+            (Zero, _) => other.clone(),
+            (_, Zero) => self.clone(),
+            (One, _) | (_, One) => One,
+            (Tuple(l1, r1), Tuple(l2, r2)) =>
+                Tuple(Rc::new(l1.union(l2)), Rc::new(r1.union(r2))).norm(),
+        }
+    }
+
+    /// Turns a plan assigning each node a range of `total` slots into ids,
+    /// cutting the id space as `owns_leaf` does. Slots past `total` are
+    /// ignored, and a node named more than once gets all of its ranges. The
+    /// ranges are expected not to overlap.
+    pub fn from_partitions<N>(assignments: &[(N, Range<usize>)], total: usize)
+            -> HashMap<N, Ident> where N: Eq + Hash + Clone {
+        let depth = total.next_power_of_two().trailing_zeros();
+        let mut ids: HashMap<N, Ident> = HashMap::new();
+
+        for (node, range) in assignments {
+            let slots = range.start.min(total) as u64..range.end.min(total) as u64;
+            let id = path::slots_ident(depth, slots);
+            let entry = ids.entry(node.clone()).or_insert(Ident::Zero);
+            *entry = entry.sum(&id);
+        }
+
+        ids
+    }
+
+    fn is_zero(&self) -> bool {
+        use Ident::*;
+
+        match *self {
+            Zero => true,
+            One => false,
+            Tuple(ref l, ref r) => l.is_zero() && r.is_zero(),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        use Ident::*;
+
+        match *self {
+            Zero => false,
+            One => true,
+            Tuple(ref l, ref r) => l.is_full() && r.is_full(),
+        }
+    }
+
+    /// Normalizes the whole tree, where `norm` only looks at the root.
+    pub fn canonical(&self) -> Ident {
+        use Ident::*;
+
+        match *self {
+            Tuple(ref l, ref r) =>
+                Tuple(Rc::new(l.canonical()), Rc::new(r.canonical())).norm(),
+            _ => self.clone(),
+        }
+    }
+
     pub fn sum(&self, other: &Ident) -> Ident {
         use Ident::*;
 
@@ -88,16 +302,43 @@ impl Ident {
             return self.clone();
         }
 
-        if let (&Tuple(ref l1, ref r1), &Tuple(ref l2, ref r2)) = (self, other) {
+        if let (Tuple(l1, r1), Tuple(l2, r2)) = (self, other) {
             return Tuple(Rc::new(l1.sum(l2)), Rc::new(r1.sum(r2))).norm();
         }
 
         // one of self or other is One, this is kind of bad!
         One
     }
+
+    /// Sums two ids into their canonical form, failing with
+    /// `ItcError::Overlap` if they share any part of the id space.
+    pub fn sum_minimal(&self, other: &Ident) -> Result<Ident, ItcError> {
+        if self.overlaps(other) {
+            return Err(ItcError::Overlap);
+        }
+
+        Ok(self.sum(other).canonical())
+    }
+
+    fn overlaps(&self, other: &Ident) -> bool {
+        use Ident::*;
+
+        match (self, other) {
+            (Zero, _) | (_, Zero) => false,
+            (One, id) | (id, One) => !id.is_zero(),
+            (Tuple(l1, r1), Tuple(l2, r2)) => l1.overlaps(l2) || r1.overlaps(r2),
+        }
+    }
 }
 
-#[derive(Clone, Eq, PartialEq, Ord)]
+/// One of the two halves produced by splitting an id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Eq, PartialEq)]
 struct Cost {
     n1: isize,
     n2: isize,
@@ -126,32 +367,78 @@ impl Cost {
     }
 }
 
-impl PartialOrd for Cost {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(match self.n2.cmp(&other.n2) {
+impl Ord for Cost {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.n2.cmp(&other.n2) {
             Ordering::Equal => self.n1.cmp(&other.n1),
             x => x
-        })
+        }
     }
 }
 
-#[derive(Eq, Clone)]
+impl PartialOrd for Cost {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Eq, Clone, Debug)]
 pub enum Event {
     Leaf(i64),
     Node(i64, Rc<Event>, Rc<Event>),
 }
 
 impl PartialEq for Event {
+    /// Compares normal forms. The same tree, or trees whose lowest or highest
+    /// counts differ, are settled first without normalizing either.
     fn eq(&self, other: &Self) -> bool {
+        if self.same_tree(other) {
+            return true;
+        }
+
+        if self.min() != other.min() || self.max() != other.max() {
+            return false;
+        }
+
         self.norm().eq_real(&other.norm())
     }
 }
 
+/// Hashes the normal form, so that events equal under `PartialEq` hash alike.
+impl Hash for Event {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.norm().fingerprint().hash(state);
+    }
+}
+
 impl Event {
     pub fn seed() -> Event {
         Event::Leaf(0)
     }
 
+    pub fn leaf(n: i64) -> Event {
+        Event::Leaf(n)
+    }
+
+    /// Builds the node `(n, left, right)` in normal form, given children that
+    /// already are: a pair of equal leaves collapses into a single leaf, and
+    /// the smaller child's value is lifted into `n`.
+    pub fn node(n: i64, left: Event, right: Event) -> Event {
+        use Event::*;
+
+        if let (&Leaf(m1), &Leaf(m2)) = (&left, &right) {
+            if m1 == m2 {
+                return Leaf(n + m1);
+            }
+        }
+
+        let m1 = left.value();
+        let m2 = right.value();
+        let m = if m1 < m2 { m1 } else { m2 };
+
+        Node(n + m, Rc::new(left.sink(m)), Rc::new(right.sink(m)))
+    }
+
     fn eq_real(&self, other: &Event) -> bool {
         use Event::*;
 
@@ -164,13 +451,50 @@ impl Event {
             Node(n, ref e1, ref e2) => match *other {
                 Node(m, ref f1, ref f2) =>
                     n == m &&
-                    e1.eq_real(&**f1) &&
-                    e2.eq_real(&**f2),
+                    e1.eq_real(f1) &&
+                    e2.eq_real(f2),
                 _ => false
             },
         }
     }
 
+    // Whether the two are the same allocation, or nodes with equal values
+    // over the same children.
+    fn same_tree(&self, other: &Event) -> bool {
+        match (self, other) {
+            (&Event::Node(n, ref e1, ref e2), &Event::Node(m, ref f1, ref f2)) =>
+                n == m && Rc::ptr_eq(e1, f1) && Rc::ptr_eq(e2, f2),
+            _ => std::ptr::eq(self, other),
+        }
+    }
+
+    // A cheap hash of the tree exactly as it is, not of its normal form.
+    fn fingerprint(&self) -> u64 {
+        use Event::*;
+
+        match *self {
+            Leaf(n) => (n as u64).wrapping_mul(0x9e3779b97f4a7c15),
+            Node(n, ref e1, ref e2) => {
+                let h = (n as u64 ^ 0x517cc1b727220a95).wrapping_mul(0x9e3779b97f4a7c15);
+                (h ^ e1.fingerprint()).rotate_left(23) ^ e2.fingerprint().rotate_left(41)
+            },
+        }
+    }
+
+    /// Whether the tree is in the normal form `norm` produces.
+    pub(crate) fn is_normal(&self) -> bool {
+        use Event::*;
+
+        match *self {
+            Leaf(_) => true,
+            Node(_, ref e1, ref e2) => match (&**e1, &**e2) {
+                (&Leaf(a), &Leaf(b)) if a == b => false,
+                _ => std::cmp::min(e1.value(), e2.value()) == 0 &&
+                    e1.is_normal() && e2.is_normal(),
+            },
+        }
+    }
+
     pub fn value(&self) -> i64 {
         use Event::*;
 
@@ -224,116 +548,735 @@ impl Event {
         }
     }
 
+    /// Number of nodes in the tree, leaves included.
+    pub fn size(&self) -> usize {
+        use Event::*;
+
+        match *self {
+            Leaf(_) => 1,
+            Node(_, ref e1, ref e2) => 1 + e1.size() + e2.size(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        use Event::*;
+
+        match *self {
+            Leaf(_) => 0,
+            Node(_, ref e1, ref e2) => 1 + std::cmp::max(e1.depth(), e2.depth()),
+        }
+    }
+
+    /// Like `depth`, but only counting the parts of the tree within the
+    /// regions `id` owns. Zero if `id` owns nothing.
+    pub fn region_depth(&self, id: &Ident) -> usize {
+        self.owned_depth(id).unwrap_or(0)
+    }
+
+    fn owned_depth(&self, id: &Ident) -> Option<usize> {
+        use Event::*;
+
+        if id.is_zero() {
+            return None;
+        }
+
+        match (self, id) {
+            (&Leaf(_), _) => Some(0),
+            (_, &Ident::One) => Some(self.depth()),
+            (Node(_, e1, e2), Ident::Tuple(i1, i2)) =>
+                std::cmp::max(e1.owned_depth(i1), e2.owned_depth(i2)).map(|d| d + 1),
+            (_, &Ident::Zero) => None,
+        }
+    }
+
+    pub fn leq(&self, other: &Event) -> bool {
+        self.leq_at(0, other, 0)
+    }
+
+    /// `self` is strictly before `other`: `other` has seen everything `self`
+    /// has, and more.
+    pub fn happens_before(&self, other: &Event) -> bool {
+        self.leq(other) && !other.leq(self)
+    }
+
+    /// `self` is strictly after `other`.
+    pub fn happens_after(&self, other: &Event) -> bool {
+        other.happens_before(self)
+    }
+
+    /// Neither event has seen everything the other has.
+    pub fn concurrent_with(&self, other: &Event) -> bool {
+        !self.leq(other) && !other.leq(self)
+    }
+
+    fn leq_at(&self, oa: i64, other: &Event, ob: i64) -> bool {
+        use Event::*;
+
+        match (self, other) {
+            (&Leaf(n), _) => oa + n <= ob + other.min(),
+
+            (&Node(..), &Leaf(m)) => oa + self.max() <= ob + m,
+
+            (&Node(n, ref l1, ref r1), &Node(m, ref l2, ref r2)) =>
+                l1.leq_at(oa + n, l2, ob + m) &&
+                r1.leq_at(oa + n, r2, ob + m),
+        }
+    }
+
+    /// Joins two events. Wherever one side already dominates the other, the
+    /// result shares that side's subtree rather than building a copy, taking
+    /// `self`'s when the two are equal, so joining events that differ in a
+    /// few regions only builds the nodes above those regions. Only the nodes
+    /// built are normalized, so the result is in normal form whenever both
+    /// inputs are. Then neither input's shape survives: the result's shape
+    /// and encoding depend only on the histories joined, never on which side
+    /// is `self`, and joining an event into itself gives it back byte for
+    /// byte.
+    pub fn join(&self, other: &Event) -> Event {
+        let (a, b) = (Rc::new(self.clone()), Rc::new(other.clone()));
+        let joined = join_shared(&a, 0, &b, 0).take(&a, 0, &b, 0);
+        Rc::try_unwrap(joined).unwrap_or_else(|e| (*e).clone())
+    }
+
+    /// Joins two events as `join` does, then flattens every subtree below
+    /// `max_depth` into a leaf holding its maximum. The result still dominates
+    /// both inputs, but may claim more history in the flattened regions.
+    pub fn join_capped(&self, other: &Event, max_depth: usize) -> Event {
+        self.join(other).truncate(max_depth).norm()
+    }
+
+    fn truncate(&self, max_depth: usize) -> Event {
+        use Event::*;
+
+        match *self {
+            Leaf(_) => self.clone(),
+            Node(..) if max_depth == 0 => Leaf(self.max()),
+            Node(n, ref l, ref r) =>
+                Node(n, Rc::new(l.truncate(max_depth - 1)), Rc::new(r.truncate(max_depth - 1))),
+        }
+    }
+
+    /// Joins all of `events`, pairing them up as a balanced tree of joins so
+    /// that intermediate results stay small. The events are normalized
+    /// first, so the result is in normal form.
+    pub fn merge_all(events: &[Event]) -> Event {
+        match merge_balanced(events.iter().map(|e| Ok::<_, Infallible>(e.norm()))) {
+            Ok(e) => e,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Returns the smallest delta for which `base.join(&delta) == *self`. It
+    /// has `self`'s value wherever `self` is ahead of `base`. Elsewhere it is
+    /// zero, or `base`'s value where that is negative, so that the join never
+    /// raises `base` there. It is a delta in the join sense rather than an
+    /// arithmetic difference.
+    pub fn subtract(&self, base: &Event) -> Result<Event, ItcError> {
+        if !base.leq(self) {
+            return Err(ItcError::NotDominating);
+        }
+
+        Ok(self.delta_from(base))
+    }
+
+    fn delta_from(&self, base: &Event) -> Event {
+        path::zip_with(self, base, &|a, b| {
+            if a > b { a } else { std::cmp::min(b, 0) }
+        }).norm()
+    }
+
+    /// Panics if a value of the normal form would overflow; see `try_norm`.
     pub fn norm(&self) -> Event {
+        match self.try_norm() {
+            Ok(e) => e,
+            Err(_) => panic!("event values overflow when normalized"),
+        }
+    }
+
+    /// Normalizes as `norm` does, failing with `ItcError::Overflow` rather
+    /// than overflowing when moving values between levels of the tree.
+    pub fn try_norm(&self) -> Result<Event, ItcError> {
         use Event::*;
 
         match *self {
-            Leaf(n) => Leaf(n),
+            Leaf(n) => Ok(Leaf(n)),
 
             Node(n, ref e1, ref e2) => {
-                let f1 = e1.norm();
-                let f2 = e2.norm();
+                let f1 = norm_shared(e1)?;
+                let f2 = norm_shared(e2)?;
 
-                if let (&Leaf(m1), &Leaf(m2)) = (&f1, &f2) {
+                if let (&Leaf(m1), &Leaf(m2)) = (&*f1, &*f2) {
                     if m1 == m2 {
-                        return Leaf(n + m1)
+                        return Ok(Leaf(checked(n.checked_add(m1))?))
                     }
                 }
 
-                let m1 = f1.min();
-                let m2 = f2.min();
+                // a normalized tree has its minimum at the root
+                let m1 = f1.value();
+                let m2 = f2.value();
                 let m = if m1 < m2 { m1 } else { m2 };
 
-                Node(n + m, Rc::new(f1.sink(m)), Rc::new(f2.sink(m)))
+                if m == 0 {
+                    return Ok(Node(n, f1, f2));
+                }
+
+                let top = checked(n.checked_add(m))?;
+                checked(m1.checked_sub(m).and(m2.checked_sub(m)))?;
+                Ok(Node(top, Rc::new((*f1).clone().sink(m)), Rc::new((*f2).clone().sink(m))))
             },
         }
     }
 
+    /// Counts the distinct tree nodes making up `events`, so that subtrees
+    /// shared between or within them are only counted once.
+    pub fn unique_node_count(events: &[&Event]) -> usize {
+        use std::collections::HashSet;
+
+        fn visit(e: &Event, seen: &mut HashSet<*const Event>) {
+            if !seen.insert(e as *const Event) {
+                return;
+            }
+
+            if let Event::Node(_, ref e1, ref e2) = *e {
+                visit(e1, seen);
+                visit(e2, seen);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        for e in events {
+            visit(e, &mut seen);
+        }
+        seen.len()
+    }
+
     pub fn event(&self, i: &Ident) -> Event {
-        let filled = self.fill(i);
+        self.event_reporting(i).0
+    }
+
+    /// Ticks as `event` does, also returning the new value of the leaf that
+    /// was grown, or `None` if the tick only filled.
+    pub(crate) fn event_reporting(&self, i: &Ident) -> (Event, Option<i64>) {
+        let (filled, changed) = self.fill(i);
 
-        if filled != *self {
-            filled
+        if changed {
+            (filled, None)
         } else {
-            let (ep, _) = self.grow(i);
-            ep
+            let (ep, _, grown) = self.grow(i);
+            (ep, Some(grown))
         }
     }
 
-    fn fill(&self, i: &Ident) -> Event {
+    /// Ticks as `event` does, also returning the delta the tick introduced,
+    /// as `subtract` would compute it, for shipping to peers.
+    pub fn advance_delta(&self, id: &Ident) -> (Event, Event) {
+        let (next, delta) = match self.fill_delta(id, 0) {
+            Some(filled) => filled,
+            None => {
+                let (ep, _, delta) = self.grow_delta(id, 0);
+                (ep, delta)
+            },
+        };
+        (next, delta.norm())
+    }
+
+    /// Ticks as `event` does, but in place, reusing the nodes of the tree
+    /// wherever they are not shared with other events. On a tree no other
+    /// event shares, filling allocates nothing and growing only allocates the
+    /// two leaves of a leaf it splits, so a loop ticking the same event
+    /// allocates nothing once the tree has grown the shape the id needs.
+    /// Shared nodes are copied only where the tick changes something under
+    /// them. The event must be in normal form.
+    pub fn event_in_place(&mut self, i: &Ident) {
+        if !self.fill_in_place(i) {
+            self.grow_in_place(i);
+        }
+    }
+
+    // Fills as `fill` does, returning whether anything changed.
+    fn fill_in_place(&mut self, i: &Ident) -> bool {
         use Ident::*;
         use Event::*;
 
-        match self {
-            &Leaf(n) => Leaf(n),
-
-            &Node(n, ref el, ref er) => match i {
-                &Zero => self.clone(),
-                &One => Leaf(self.max()),
-
-                &Tuple(ref il, ref ir) => {
-                    if let &One = &**il {
-                        let ep = er.fill(ir);
-                        let ml = el.max();
-                        let mr = ep.max();
-                        let m = if ml > mr { ml } else { mr };
-                        return Node(n, Rc::new(Leaf(m)), Rc::new(ep)).norm();
+        let changed = match (&mut *self, i) {
+            (&mut Leaf(_), _) | (_, &Zero) => return false,
+
+            (_, &One) => true,
+
+            (&mut Node(_, ref mut el, ref mut er), Tuple(il, ir)) => {
+                if let One = **il {
+                    let changed = fill_child(er, ir);
+                    let m = std::cmp::max(el.max(), er.max());
+                    let raised = el.min() != m;
+                    if raised {
+                        replace_child(el, Leaf(m));
                     }
+                    changed || raised
+                } else if let One = **ir {
+                    let changed = fill_child(el, il);
+                    let m = std::cmp::max(el.max(), er.max());
+                    let raised = er.min() != m;
+                    if raised {
+                        replace_child(er, Leaf(m));
+                    }
+                    changed || raised
+                } else {
+                    let left = fill_child(el, il);
+                    let right = fill_child(er, ir);
+                    left || right
+                }
+            },
+        };
+
+        if let One = *i {
+            *self = Leaf(self.max());
+        } else if changed {
+            self.norm_root_in_place();
+        }
+
+        changed
+    }
 
-                    if let &One = &**ir {
-                        let ep = el.fill(il);
-                        let ml = ep.max();
-                        let mr = er.max();
-                        let m = if ml > mr { ml } else { mr };
-                        return Node(n, Rc::new(ep), Rc::new(Leaf(m))).norm();
+    // Grows as `grow` does.
+    fn grow_in_place(&mut self, i: &Ident) {
+        use Ident::*;
+        use Event::*;
+
+        if let Leaf(n) = *self {
+            match *i {
+                One => *self = Leaf(n + 1),
+                _ => {
+                    *self = Node(n, Rc::new(Leaf(0)), Rc::new(Leaf(0)));
+                    self.grow_in_place(i);
+                },
+            }
+            return;
+        }
+
+        if let Node(_, ref mut el, ref mut er) = *self {
+            match *i {
+                One | Zero => panic!("ITC internal error!"),
+
+                Tuple(ref il, ref ir) => {
+                    if let Zero = **il {
+                        Rc::make_mut(er).grow_in_place(ir);
+                    } else if let Zero = **ir {
+                        Rc::make_mut(el).grow_in_place(il);
+                    } else if el.grow_cost(il) < er.grow_cost(ir) {
+                        Rc::make_mut(el).grow_in_place(il);
+                    } else {
+                        Rc::make_mut(er).grow_in_place(ir);
                     }
+                },
+            }
+        }
+    }
+
+    // What `grow` would cost, without building anything.
+    fn grow_cost(&self, i: &Ident) -> Cost {
+        use Ident::*;
+        use Event::*;
 
-                    Node(n, Rc::new(el.fill(il)), Rc::new(er.fill(ir))).norm()
+        match (self, i) {
+            (Leaf(_), One) => Cost::zero(),
+            (Leaf(_), Tuple(il, ir)) => node_grow_cost(&Leaf(0), &Leaf(0), il, ir).inc2(),
+            (Node(_, el, er), Tuple(il, ir)) => node_grow_cost(el, er, il, ir),
+            _ => panic!("ITC internal error!"),
+        }
+    }
+
+    // Normalizes a node whose children are already normalized.
+    fn norm_root_in_place(&mut self) {
+        use Event::*;
+
+        if let Node(ref mut n, ref mut e1, ref mut e2) = *self {
+            if let (Leaf(m1), Leaf(m2)) = (&**e1, &**e2) {
+                if m1 == m2 {
+                    *self = Leaf(*n + *m1);
+                    return;
                 }
             }
+
+            let m = std::cmp::min(e1.value(), e2.value());
+            if m != 0 {
+                *n += m;
+                *Rc::make_mut(e1).value_mut() -= m;
+                *Rc::make_mut(e2).value_mut() -= m;
+            }
+        }
+    }
+
+    fn value_mut(&mut self) -> &mut i64 {
+        match *self {
+            Event::Leaf(ref mut n) | Event::Node(ref mut n, _, _) => n,
         }
     }
 
-    fn grow(&self, i: &Ident) -> (Event, Cost) {
+    // Also returns whether filling raised anything, working it out on the way
+    // down rather than by comparing trees afterwards. Only the nodes on the
+    // way to a raised region are rebuilt, each normalized as it is.
+    fn fill(&self, i: &Ident) -> (Event, bool) {
         use Ident::*;
         use Event::*;
 
-        match self {
-            &Leaf(n) => {
-                if let &One = i {
-                    (Leaf(n + 1), Cost::zero())
+        match *self {
+            Leaf(n) => (Leaf(n), false),
+
+            Node(n, ref el, ref er) => match *i {
+                Zero => (self.clone(), false),
+                One => {
+                    let m = self.max();
+                    (Leaf(m), self.min() != m)
+                },
+
+                Tuple(ref il, ref ir) => {
+                    let (left, right, changed) = if let One = **il {
+                        let (ep, changed) = er.fill(ir);
+                        let m = std::cmp::max(el.max(), ep.max());
+                        (Leaf(m), ep, changed || el.min() != m)
+                    } else if let One = **ir {
+                        let (ep, changed) = el.fill(il);
+                        let m = std::cmp::max(ep.max(), er.max());
+                        (ep, Leaf(m), changed || er.min() != m)
+                    } else {
+                        let ((l, cl), (r, cr)) = (el.fill(il), er.fill(ir));
+                        (l, r, cl || cr)
+                    };
+
+                    if changed {
+                        (Event::node(n, left, right), true)
+                    } else {
+                        (self.clone(), false)
+                    }
+                }
+            }
+        }
+    }
+
+    // Also returns the new value of the grown leaf, relative to this node's
+    // parent.
+    fn grow(&self, i: &Ident) -> (Event, Cost, i64) {
+        use Ident::*;
+        use Event::*;
+
+        match *self {
+            Leaf(n) => {
+                if let One = *i {
+                    (Leaf(n + 1), Cost::zero(), n + 1)
                 } else {
-                    let (e, c) = Node(
+                    let (e, c, v) = Node(
                             n, Rc::new(Leaf(0)), Rc::new(Leaf(0))
                         ).grow(i);
-                    (e, c.inc2())
+                    (e, c.inc2(), v)
                 }
             },
 
-            &Node(n, ref el, ref er) => match i {
-                &One | &Zero => panic!("ITC internal error!"),
+            Node(n, ref el, ref er) => match *i {
+                One | Zero => panic!("ITC internal error!"),
 
-                &Tuple(ref il, ref ir) => {
+                Tuple(ref il, ref ir) => {
                     if let &Zero = &**il {
-                        let (ep, c) = er.grow(ir);
-                        return (Node(n, el.clone(), Rc::new(ep)), c.inc1());
+                        let (ep, c, v) = er.grow(ir);
+                        return (Node(n, el.clone(), Rc::new(ep)), c.inc1(), n + v);
                     }
 
                     if let &Zero = &**ir {
-                        let (ep, c) = el.grow(il);
-                        return (Node(n, Rc::new(ep), er.clone()), c.inc1());
+                        let (ep, c, v) = el.grow(il);
+                        return (Node(n, Rc::new(ep), er.clone()), c.inc1(), n + v);
                     }
 
-                    let (elp, cl) = el.grow(il);
-                    let (erp, cr) = er.grow(ir);
+                    let (elp, cl, vl) = el.grow(il);
+                    let (erp, cr, vr) = er.grow(ir);
 
                     if cl < cr {
-                        (Node(n, Rc::new(elp), er.clone()), cl.inc1())
+                        (Node(n, Rc::new(elp), er.clone()), cl.inc1(), n + vl)
                     } else {
-                        (Node(n, el.clone(), Rc::new(erp)), cr.inc1())
+                        (Node(n, el.clone(), Rc::new(erp)), cr.inc1(), n + vr)
                     }
                 }
             }
         }
     }
+
+    // Fills as `fill` does, also building the delta of the raised regions as
+    // `raised` does; `None` if nothing could be filled. `base` is the absolute
+    // value of this node's parent.
+    fn fill_delta(&self, i: &Ident, base: i64) -> Option<(Event, Event)> {
+        use Ident::*;
+        use Event::*;
+
+        let (n, el, er) = match *self {
+            Leaf(_) => return None,
+            Node(n, ref el, ref er) => (n, el, er),
+        };
+
+        let (il, ir) = match *i {
+            Zero => return None,
+            One => {
+                let m = self.max();
+                return raised(self, base, base + m).map(|delta| (Leaf(m), delta));
+            },
+            Tuple(ref il, ref ir) => (il, ir),
+        };
+
+        let (left, right) = match (&**il, &**ir) {
+            (&One, _) => {
+                let right = er.fill_delta(ir, base + n);
+                let ep = right.as_ref().map_or(&**er, |r| &r.0);
+                let m = std::cmp::max(el.max(), ep.max());
+                let left = raised(el, base + n, base + n + m).map(|d| (Leaf(m), d));
+                (left, right)
+            },
+
+            (_, &One) => {
+                let left = el.fill_delta(il, base + n);
+                let ep = left.as_ref().map_or(&**el, |l| &l.0);
+                let m = std::cmp::max(ep.max(), er.max());
+                let right = raised(er, base + n, base + n + m).map(|d| (Leaf(m), d));
+                (left, right)
+            },
+
+            _ => (el.fill_delta(il, base + n), er.fill_delta(ir, base + n)),
+        };
+
+        if left.is_none() && right.is_none() {
+            return None;
+        }
+
+        let (elp, dl) = left.unwrap_or(((**el).clone(), Leaf(0)));
+        let (erp, dr) = right.unwrap_or(((**er).clone(), Leaf(0)));
+        let delta = Node(0, Rc::new(dl), Rc::new(dr));
+        Some((Node(n, Rc::new(elp), Rc::new(erp)).norm(), delta))
+    }
+
+    // Grows as `grow` does, also building the delta of the grown leaf, with
+    // its absolute value, zero elsewhere. `base` is the absolute value of this
+    // node's parent.
+    fn grow_delta(&self, i: &Ident, base: i64) -> (Event, Cost, Event) {
+        use Ident::*;
+        use Event::*;
+
+        match *self {
+            Leaf(n) => {
+                if let One = *i {
+                    (Leaf(n + 1), Cost::zero(), Leaf(base + n + 1))
+                } else {
+                    let (e, c, d) = Node(
+                            n, Rc::new(Leaf(0)), Rc::new(Leaf(0))
+                        ).grow_delta(i, base);
+                    (e, c.inc2(), d)
+                }
+            },
+
+            Node(n, ref el, ref er) => match *i {
+                One | Zero => panic!("ITC internal error!"),
+
+                Tuple(ref il, ref ir) => {
+                    let zero = Rc::new(Leaf(0));
+
+                    if let &Zero = &**il {
+                        let (ep, c, d) = er.grow_delta(ir, base + n);
+                        let delta = Node(0, zero, Rc::new(d));
+                        return (Node(n, el.clone(), Rc::new(ep)), c.inc1(), delta);
+                    }
+
+                    if let &Zero = &**ir {
+                        let (ep, c, d) = el.grow_delta(il, base + n);
+                        let delta = Node(0, Rc::new(d), zero);
+                        return (Node(n, Rc::new(ep), er.clone()), c.inc1(), delta);
+                    }
+
+                    let (elp, cl, dl) = el.grow_delta(il, base + n);
+                    let (erp, cr, dr) = er.grow_delta(ir, base + n);
+
+                    if cl < cr {
+                        let delta = Node(0, Rc::new(dl), zero);
+                        (Node(n, Rc::new(elp), er.clone()), cl.inc1(), delta)
+                    } else {
+                        let delta = Node(0, zero, Rc::new(dr));
+                        (Node(n, el.clone(), Rc::new(erp)), cr.inc1(), delta)
+                    }
+                }
+            }
+        }
+    }
+}
+
+// What `grow` would cost at a node with children `el` and `er`.
+fn node_grow_cost(el: &Event, er: &Event, il: &Ident, ir: &Ident) -> Cost {
+    if let Ident::Zero = *il {
+        return er.grow_cost(ir).inc1();
+    }
+
+    if let Ident::Zero = *ir {
+        return el.grow_cost(il).inc1();
+    }
+
+    std::cmp::min(el.grow_cost(il), er.grow_cost(ir)).inc1()
+}
+
+// Fills a child in place if no other event shares it, and otherwise fills a
+// copy, keeping the shared child if nothing changed.
+fn fill_child(e: &mut Rc<Event>, i: &Ident) -> bool {
+    if let Some(e) = Rc::get_mut(e) {
+        return e.fill_in_place(i);
+    }
+
+    let (filled, changed) = e.fill(i);
+    if changed {
+        *e = Rc::new(filled);
+    }
+    changed
+}
+
+// Overwrites a child in place if no other event shares it.
+fn replace_child(e: &mut Rc<Event>, value: Event) {
+    match Rc::get_mut(e) {
+        Some(e) => *e = value,
+        None => *e = Rc::new(value),
+    }
+}
+
+// The tree of `e` raised to `target` wherever it is below it, and zero
+// elsewhere, with the leaves holding absolute values; `None` if nothing is
+// below. `base` is the absolute value of `e`'s parent.
+fn raised(e: &Event, base: i64, target: i64) -> Option<Event> {
+    use Event::*;
+
+    match *e {
+        Leaf(n) => if base + n < target { Some(Leaf(target)) } else { None },
+
+        Node(n, ref el, ref er) => {
+            match (raised(el, base + n, target), raised(er, base + n, target)) {
+                (None, None) => None,
+                (l, r) => Some(Node(0,
+                    Rc::new(l.unwrap_or(Leaf(0))),
+                    Rc::new(r.unwrap_or(Leaf(0))))),
+            }
+        },
+    }
+}
+
+// Normalizes a subtree, handing back the same allocation if it already was.
+fn norm_shared(e: &Rc<Event>) -> Result<Rc<Event>, ItcError> {
+    if let Event::Node(n, ref c1, ref c2) = **e {
+        let f = e.try_norm()?;
+
+        if let Event::Node(m, ref f1, ref f2) = f {
+            if m == n && Rc::ptr_eq(f1, c1) && Rc::ptr_eq(f2, c2) {
+                return Ok(e.clone());
+            }
+        }
+
+        return Ok(Rc::new(f));
+    }
+
+    Ok(e.clone())
+}
+
+fn checked<T>(value: Option<T>) -> Result<T, ItcError> {
+    value.ok_or(ItcError::Overflow)
+}
+
+// Joins `a` lifted by `da` with `b` lifted by `db` in a single walk over both.
+// A result equal to one side is left for the caller to take from that side,
+// so that wherever one side dominates the result reuses its subtree. A leaf
+// facing a node is walked as a node with two zero leaves.
+fn join_shared(a: &Rc<Event>, da: i64, b: &Rc<Event>, db: i64) -> Joined {
+    use Event::*;
+
+    thread_local!(static ZERO: Rc<Event> = Rc::new(Leaf(0)));
+
+    if da == db && Rc::ptr_eq(a, b) {
+        return Joined::Same;
+    }
+
+    if let (&Leaf(n1), &Leaf(n2)) = (&**a, &**b) {
+        return match (n1 + da).cmp(&(n2 + db)) {
+            Ordering::Less => Joined::Right,
+            Ordering::Equal => Joined::Same,
+            Ordering::Greater => Joined::Left,
+        };
+    }
+
+    let children = |e: &Rc<Event>| match **e {
+        Leaf(n) => ZERO.with(|zero| (n, zero.clone(), zero.clone())),
+        Node(n, ref l, ref r) => (n, l.clone(), r.clone()),
+    };
+
+    let ((n1, l1, r1), (n2, l2, r2)) = (children(a), children(b));
+    let (m1, m2) = (n1 + da, n2 + db);
+    let n = std::cmp::min(m1, m2);
+    let (d1, d2) = (m1 - n, m2 - n);
+
+    match (join_shared(&l1, d1, &l2, d2), join_shared(&r1, d1, &r2, d2)) {
+        (Joined::Same, Joined::Same) => Joined::Same,
+        (Joined::Left, Joined::Left) | (Joined::Left, Joined::Same) |
+            (Joined::Same, Joined::Left) => Joined::Left,
+        (Joined::Right, Joined::Right) | (Joined::Right, Joined::Same) |
+            (Joined::Same, Joined::Right) => Joined::Right,
+        (l, r) => Joined::New(node_shared(n, l.take(&l1, d1, &l2, d2), r.take(&r1, d1, &r2, d2))),
+    }
+}
+
+// Builds the node `(n, l, r)` as `Event::node` does, keeping the children's
+// allocations unless their values have to sink.
+fn node_shared(n: i64, l: Rc<Event>, r: Rc<Event>) -> Rc<Event> {
+    use Event::*;
+
+    if let (&Leaf(m1), &Leaf(m2)) = (&*l, &*r) {
+        if m1 == m2 {
+            return Rc::new(Leaf(n + m1));
+        }
+    }
+
+    let m = std::cmp::min(l.value(), r.value());
+    Rc::new(Node(n + m, lifted(&l, -m), lifted(&r, -m)))
+}
+
+// The result of `join_shared`: equal to both sides, to one of them, or new.
+enum Joined {
+    Same,
+    Left,
+    Right,
+    New(Rc<Event>),
+}
+
+impl Joined {
+    fn take(self, a: &Rc<Event>, da: i64, b: &Rc<Event>, db: i64) -> Rc<Event> {
+        match self {
+            Joined::Same | Joined::Left => lifted(a, da),
+            Joined::Right => lifted(b, db),
+            Joined::New(e) => e,
+        }
+    }
+}
+
+fn lifted(e: &Rc<Event>, d: i64) -> Rc<Event> {
+    if d == 0 { e.clone() } else { Rc::new((**e).clone().lift(d)) }
+}
+
+// Joins a stream of events as they arrive, keeping a stack of partial results
+// in which each entry covers twice as many inputs as the one above it.
+pub(crate) fn merge_balanced<I, E>(events: I) -> Result<Event, E>
+        where I: Iterator<Item = Result<Event, E>> {
+    let mut stack: Vec<(u32, Event)> = Vec::new();
+
+    for e in events {
+        let mut e = e?;
+        let mut level = 0;
+
+        while stack.last().is_some_and(|&(l, _)| l == level) {
+            let (_, top) = stack.pop().unwrap();
+            e = top.join(&e);
+            level += 1;
+        }
+
+        stack.push((level, e));
+    }
+
+    Ok(match stack.pop() {
+        Some((_, last)) => stack.into_iter().rev().fold(last, |acc, (_, e)| e.join(&acc)),
+        None => Event::seed(),
+    })
 }
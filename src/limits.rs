@@ -0,0 +1,106 @@
+//! Hard caps on how large stamps may grow.
+//!
+//! A bug or a hostile peer can make clocks grow without bound. The checked
+//! stamp operations take a `Limits` and fail with `ItcError::LimitExceeded`,
+//! rather than hand back a stamp whose id or event is deeper, or whose event
+//! has more nodes, than it allows. Trees built from parts, such as those
+//! read off the wire, can be held to the same limits with the checked
+//! constructors.
+
+use std::rc::Rc;
+
+use Event;
+use Ident;
+use ItcError;
+use Stamp;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_size: usize,
+}
+
+impl Limits {
+    pub fn new(max_depth: usize, max_size: usize) -> Limits {
+        Limits { max_depth, max_size }
+    }
+
+    pub fn unlimited() -> Limits {
+        Limits::new(usize::MAX, usize::MAX)
+    }
+
+    pub fn check(&self, stamp: &Stamp) -> Result<(), ItcError> {
+        self.check_ident(&stamp.id)?;
+        self.check_event(&stamp.event)
+    }
+
+    pub fn check_ident(&self, id: &Ident) -> Result<(), ItcError> {
+        if id.depth() > self.max_depth {
+            return Err(ItcError::LimitExceeded);
+        }
+
+        Ok(())
+    }
+
+    pub fn check_event(&self, event: &Event) -> Result<(), ItcError> {
+        if event.depth() > self.max_depth || event.size() > self.max_size {
+            return Err(ItcError::LimitExceeded);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits::unlimited()
+    }
+}
+
+impl Ident {
+    /// Builds the tuple `(left, right)`, failing if it breaks `limits`.
+    pub fn try_tuple(left: Ident, right: Ident, limits: &Limits) -> Result<Ident, ItcError> {
+        let id = Ident::Tuple(Rc::new(left), Rc::new(right));
+        limits.check_ident(&id)?;
+        Ok(id)
+    }
+}
+
+impl Event {
+    /// Builds the node `(n, left, right)` as `Event::node` does, failing if it
+    /// breaks `limits`.
+    pub fn try_node(n: i64, left: Event, right: Event, limits: &Limits)
+            -> Result<Event, ItcError> {
+        let event = Event::node(n, left, right);
+        limits.check_event(&event)?;
+        Ok(event)
+    }
+}
+
+impl Stamp {
+    /// Builds a stamp as `Stamp::new` does, failing if it breaks `limits`.
+    pub fn try_new(id: Ident, event: Event, limits: &Limits) -> Result<Stamp, ItcError> {
+        let stamp = Stamp::new(id, event);
+        limits.check(&stamp)?;
+        Ok(stamp)
+    }
+
+    pub fn try_fork(&self, limits: &Limits) -> Result<(Stamp, Stamp), ItcError> {
+        let (a, b) = self.fork();
+        limits.check(&a)?;
+        limits.check(&b)?;
+        Ok((a, b))
+    }
+
+    pub fn try_event(&self, limits: &Limits) -> Result<Stamp, ItcError> {
+        let next = self.event();
+        limits.check(&next)?;
+        Ok(next)
+    }
+
+    pub fn try_join(&self, other: &Stamp, limits: &Limits) -> Result<Stamp, ItcError> {
+        let joined = self.join(other);
+        limits.check(&joined)?;
+        Ok(joined)
+    }
+}
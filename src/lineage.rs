@@ -0,0 +1,154 @@
+//! Fork lineage proofs for ids.
+//!
+//! A `LineageIdent` is an id that remembers how it was forked off a seed. Every
+//! fork appends a link whose digest covers the previous link, the side taken and
+//! the resulting id, so the chain can later be checked against the seed's digest.
+//!
+//! The seed's digest is derived from a secret and keys every link, as an
+//! HMAC-SHA256. It must be kept as secret as the secret itself: whoever holds
+//! it can fork, and so mint a proof for any id, while without it a proof can
+//! be neither forged nor extended, not even from the links of a valid one.
+
+use sha2::{Digest as ShaDigest, Sha256};
+
+use Ident;
+use Side;
+
+pub type Digest = [u8; 32];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineageStep {
+    pub side: Side,
+    pub digest: Digest,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineageProof {
+    pub id: Ident,
+    pub steps: Vec<LineageStep>,
+}
+
+#[derive(Clone, Debug)]
+pub struct LineageIdent {
+    id: Ident,
+    seed: Digest,
+    steps: Vec<LineageStep>,
+}
+
+impl LineageIdent {
+    /// Creates a seed id whose lineage is rooted at `seed_digest(secret)`.
+    pub fn seed(secret: &[u8]) -> LineageIdent {
+        LineageIdent {
+            id: Ident::seed(),
+            seed: seed_digest(secret),
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn ident(&self) -> &Ident {
+        &self.id
+    }
+
+    pub fn split(&self) -> (LineageIdent, LineageIdent) {
+        let (l, r) = self.id.split();
+        (self.extend(l, Side::Left), self.extend(r, Side::Right))
+    }
+
+    pub fn prove_lineage(&self) -> LineageProof {
+        LineageProof {
+            id: self.id.clone(),
+            steps: self.steps.clone(),
+        }
+    }
+
+    fn extend(&self, id: Ident, side: Side) -> LineageIdent {
+        let prev = match self.steps.last() {
+            Some(step) => step.digest,
+            None => self.seed,
+        };
+
+        let mut steps = self.steps.clone();
+        steps.push(LineageStep {
+            side,
+            digest: link_digest(&self.seed, &prev, side, &id),
+        });
+
+        LineageIdent {
+            id,
+            seed: self.seed,
+            steps,
+        }
+    }
+}
+
+pub fn seed_digest(secret: &[u8]) -> Digest {
+    let mut h = Sha256::new();
+    h.update(b"itc-lineage-seed");
+    h.update(secret);
+    hash_ident(&mut h, &Ident::seed());
+    h.finalize().into()
+}
+
+/// Checks that `proof` is an unbroken chain of forks from the seed with the
+/// given digest down to `proof.id`.
+pub fn verify_lineage(proof: &LineageProof, seed_digest: &Digest) -> bool {
+    let mut id = Ident::seed();
+    let mut prev = *seed_digest;
+
+    for step in &proof.steps {
+        let (l, r) = id.split();
+        id = match step.side {
+            Side::Left => l,
+            Side::Right => r,
+        };
+
+        if link_digest(seed_digest, &prev, step.side, &id) != step.digest {
+            return false;
+        }
+
+        prev = step.digest;
+    }
+
+    id == proof.id
+}
+
+fn link_digest(key: &Digest, prev: &Digest, side: Side, id: &Ident) -> Digest {
+    let mut h = Sha256::new();
+    h.update(pad(key, 0x36));
+    h.update(prev);
+    h.update([match side {
+        Side::Left => 0u8,
+        Side::Right => 1u8,
+    }]);
+    hash_ident(&mut h, id);
+    let inner: Digest = h.finalize().into();
+
+    let mut h = Sha256::new();
+    h.update(pad(key, 0x5c));
+    h.update(inner);
+    h.finalize().into()
+}
+
+// The HMAC key block, `key` padded to SHA-256's block size and XORed with
+// `byte`.
+fn pad(key: &Digest, byte: u8) -> [u8; 64] {
+    let mut block = [byte; 64];
+
+    for (b, k) in block.iter_mut().zip(key) {
+        *b ^= k;
+    }
+
+    block
+}
+
+fn hash_ident(h: &mut Sha256, id: &Ident) {
+    match *id {
+        Ident::Zero => h.update([0u8]),
+        Ident::One => h.update([1u8]),
+        Ident::Tuple(ref l, ref r) => {
+            h.update([2u8]);
+            hash_ident(h, l);
+            hash_ident(h, r);
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Recorded histories of stamp operations.
+//!
+//! A `StampLog` records forks, ticks, merges and joins among a group of
+//! stamps grown from a single seed, numbering members as `ForkOp` does, so
+//! that a history can be replayed and audited later.
+
+use Stamp;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogOp {
+    /// The member forks, keeping one half and handing the other to a new
+    /// member numbered after all the others.
+    Fork(usize),
+    /// The member ticks.
+    Event(usize),
+    /// The first member learns what the second has seen, as from a message,
+    /// and both stay.
+    Merge(usize, usize),
+    /// The second member leaves, joining into the first.
+    Join(usize, usize),
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StampLog {
+    ops: Vec<LogOp>,
+}
+
+impl StampLog {
+    pub fn new() -> StampLog {
+        StampLog { ops: Vec::new() }
+    }
+
+    pub fn push(&mut self, op: LogOp) {
+        self.ops.push(op);
+    }
+
+    pub fn ops(&self) -> &[LogOp] {
+        &self.ops
+    }
+
+    /// Replays the log from a seed, returning the members still present at
+    /// the end, in order of their numbers.
+    ///
+    /// Panics if an op names a member that does not exist or has left, or
+    /// joins a member with itself.
+    pub fn replay(&self) -> Vec<Stamp> {
+        let mut members = vec![Some(Stamp::seed())];
+
+        for op in &self.ops {
+            match *op {
+                LogOp::Fork(m) => {
+                    let (kept, given) = member(&members, m).fork();
+                    members[m] = Some(kept);
+                    members.push(Some(given));
+                },
+                LogOp::Event(m) => members[m] = Some(member(&members, m).event()),
+                LogOp::Merge(to, from) => {
+                    let merged = member(&members, to).join(&member(&members, from).peek());
+                    members[to] = Some(merged);
+                },
+                LogOp::Join(heir, departing) => {
+                    assert!(heir != departing, "member {} cannot join itself", heir);
+                    let joined = member(&members, heir).join(member(&members, departing));
+                    members[heir] = Some(joined);
+                    members[departing] = None;
+                },
+            }
+        }
+
+        members.into_iter().flatten().collect()
+    }
+}
+
+/// Removes operations that cancel out or make no difference, keeping what
+/// `replay` returns. A fork whose new member is at once joined back into its
+/// parent leaves the parent as it was, and members created later are
+/// renumbered to close the gap. A merge is superseded by a later merge of the
+/// same members if, in between, the receiving member is not ticked, forked
+/// or read from, since its event only gains what the later merge brings
+/// anyway.
+pub fn compact_log(log: &StampLog) -> StampLog {
+    let mut ops = log.ops.clone();
+
+    loop {
+        if let Some(i) = superseded_merge(&ops) {
+            ops.remove(i);
+            continue;
+        }
+
+        let i = match cancelling_fork(&ops) {
+            Some(i) => i,
+            None => break,
+        };
+        let removed = members_before(&ops[..i]);
+        ops.drain(i..i + 2);
+
+        for op in &mut ops[i..] {
+            *op = match *op {
+                LogOp::Fork(m) => LogOp::Fork(renumber(m, removed)),
+                LogOp::Event(m) => LogOp::Event(renumber(m, removed)),
+                LogOp::Merge(a, b) => LogOp::Merge(renumber(a, removed), renumber(b, removed)),
+                LogOp::Join(a, b) => LogOp::Join(renumber(a, removed), renumber(b, removed)),
+            };
+        }
+    }
+
+    StampLog { ops }
+}
+
+// Finds a merge repeated later with nothing in between that sees the
+// receiving member's event or ticks on it.
+fn superseded_merge(ops: &[LogOp]) -> Option<usize> {
+    (0..ops.len()).find(|&i| match ops[i] {
+        LogOp::Merge(to, from) => ops[i + 1..].iter()
+            .take_while(|&&op| !observes(op, to))
+            .any(|&op| op == LogOp::Merge(to, from)),
+        _ => false,
+    })
+}
+
+// Whether `op` depends on member `m`'s event other than by only adding to it.
+fn observes(op: LogOp, m: usize) -> bool {
+    match op {
+        LogOp::Fork(k) | LogOp::Event(k) => k == m,
+        LogOp::Merge(_, from) => from == m,
+        LogOp::Join(_, departing) => departing == m,
+    }
+}
+
+// Finds a fork immediately followed by the parent absorbing the new member.
+fn cancelling_fork(ops: &[LogOp]) -> Option<usize> {
+    (0..ops.len().saturating_sub(1)).find(|&i| match (ops[i], ops[i + 1]) {
+        (LogOp::Fork(m), LogOp::Join(heir, departing)) =>
+            heir == m && departing == members_before(&ops[..i]),
+        _ => false,
+    })
+}
+
+// How many members have been numbered after `ops`, departed ones included.
+fn members_before(ops: &[LogOp]) -> usize {
+    1 + ops.iter().filter(|op| matches!(op, LogOp::Fork(_))).count()
+}
+
+fn renumber(m: usize, removed: usize) -> usize {
+    if m > removed { m - 1 } else { m }
+}
+
+fn member(members: &[Option<Stamp>], m: usize) -> &Stamp {
+    match members.get(m) {
+        Some(Some(stamp)) => stamp,
+        _ => panic!("member {} does not exist", m),
+    }
+}
@@ -0,0 +1,92 @@
+//! Per-key registers ordered by causality.
+//!
+//! A `StampMap` holds, for each key, the latest value written and the event
+//! it was written at. A write the current value already dominates is dropped,
+//! a write dominating it replaces it, and a concurrent write is handed to the
+//! merge callback together with the current value.
+
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
+use Event;
+use Stamp;
+
+/// What `StampMap::insert` did with a write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InsertOutcome {
+    /// The key was new, or the write dominated the current value.
+    Stored,
+    /// The current value already dominated the write.
+    Dropped,
+    /// The write was concurrent with the current value, and the two were
+    /// merged at the join of their events.
+    Merged,
+}
+
+type Merge<K, V> = Box<dyn FnMut(&K, &V, &V) -> V>;
+
+pub struct StampMap<K, V> {
+    entries: BTreeMap<K, (Event, V)>,
+    merge: Merge<K, V>,
+}
+
+impl<K: Ord, V> StampMap<K, V> {
+    /// Creates an empty map which resolves concurrent writes with `merge`,
+    /// called with the key, the current value and the written one.
+    pub fn new<F>(merge: F) -> StampMap<K, V>
+            where F: FnMut(&K, &V, &V) -> V + 'static {
+        StampMap {
+            entries: BTreeMap::new(),
+            merge: Box::new(merge),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, stamp: &Stamp, value: V) -> InsertOutcome {
+        let (event, current) = match self.entries.get_mut(&key) {
+            None => {
+                self.entries.insert(key, (stamp.event.norm(), value));
+                return InsertOutcome::Stored;
+            },
+            Some(entry) => entry,
+        };
+
+        if stamp.event.leq(event) {
+            return InsertOutcome::Dropped;
+        }
+
+        if event.leq(&stamp.event) {
+            *event = stamp.event.norm();
+            *current = value;
+            return InsertOutcome::Stored;
+        }
+
+        *current = (self.merge)(&key, current, &value);
+        *event = event.join(&stamp.event);
+        InsertOutcome::Merged
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, v)| v)
+    }
+
+    /// The event the value for `key` was written at.
+    pub fn event(&self, key: &K) -> Option<&Event> {
+        self.entries.get(key).map(|(e, _)| e)
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(key).map(|(_, v)| v)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> btree_map::Iter<'_, K, (Event, V)> {
+        self.entries.iter()
+    }
+}
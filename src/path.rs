@@ -0,0 +1,331 @@
+//! Addressing regions of the id space.
+//!
+//! Both id and event trees halve the unit interval at every level, so a region
+//! is named by the sequence of halves taken from the root.
+
+use std::ops::Range;
+use std::rc::Rc;
+
+use Event;
+use Ident;
+use ItcError;
+use Side;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct IdentPath {
+    steps: Vec<Side>,
+}
+
+impl IdentPath {
+    pub fn root() -> IdentPath {
+        IdentPath { steps: Vec::new() }
+    }
+
+    pub fn new(steps: Vec<Side>) -> IdentPath {
+        IdentPath { steps }
+    }
+
+    pub fn steps(&self) -> &[Side] {
+        &self.steps
+    }
+
+    pub fn depth(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn child(&self, side: Side) -> IdentPath {
+        let mut steps = self.steps.clone();
+        steps.push(side);
+        IdentPath { steps }
+    }
+
+    /// Whether one of the two regions contains the other.
+    pub fn overlaps(&self, other: &IdentPath) -> bool {
+        self.steps.iter().zip(other.steps.iter()).all(|(a, b)| a == b)
+    }
+
+    /// Returns the id owning exactly this region.
+    pub fn to_ident(&self) -> Ident {
+        self.steps.iter().rev().fold(Ident::One, |id, side| match *side {
+            Side::Left => Ident::Tuple(Rc::new(id), Rc::new(Ident::Zero)),
+            Side::Right => Ident::Tuple(Rc::new(Ident::Zero), Rc::new(id)),
+        })
+    }
+
+    pub(crate) fn push(&mut self, side: Side) {
+        self.steps.push(side);
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.steps.pop();
+    }
+}
+
+impl Ident {
+    /// Lists the maximal regions this id owns, left to right.
+    pub fn regions(&self) -> Vec<IdentPath> {
+        fn visit(id: &Ident, path: &mut IdentPath, out: &mut Vec<IdentPath>) {
+            match *id {
+                Ident::Zero => {},
+                Ident::One => out.push(path.clone()),
+                Ident::Tuple(ref l, ref r) => {
+                    path.push(Side::Left);
+                    visit(l, path, out);
+                    path.pop();
+                    path.push(Side::Right);
+                    visit(r, path, out);
+                    path.pop();
+                },
+            }
+        }
+
+        let mut out = Vec::new();
+        visit(&self.canonical(), &mut IdentPath::root(), &mut out);
+        out
+    }
+
+    /// The thinnest child a single fork of this id could hand out: the right
+    /// half of its smallest region, leftmost on ties. `None` for `Zero`.
+    pub fn smallest_forkable(&self) -> Option<Ident> {
+        let mut smallest: Option<IdentPath> = None;
+
+        for region in self.regions() {
+            if smallest.as_ref().is_none_or(|s| region.depth() > s.depth()) {
+                smallest = Some(region);
+            }
+        }
+
+        smallest.map(|region| region.child(Side::Right).to_ident())
+    }
+}
+
+impl Event {
+    /// Lists every leaf of the tree, left to right, with its region and the
+    /// absolute value the event takes there.
+    pub fn leaf_values(&self) -> Vec<(IdentPath, i64)> {
+        fn visit(e: &Event, base: i64, path: &mut IdentPath, out: &mut Vec<(IdentPath, i64)>) {
+            match *e {
+                Event::Leaf(n) => out.push((path.clone(), base + n)),
+                Event::Node(n, ref l, ref r) => {
+                    path.push(Side::Left);
+                    visit(l, base + n, path, out);
+                    path.pop();
+                    path.push(Side::Right);
+                    visit(r, base + n, path, out);
+                    path.pop();
+                },
+            }
+        }
+
+        let mut out = Vec::new();
+        visit(self, 0, &mut IdentPath::root(), &mut out);
+        out
+    }
+
+    /// Sets the event to exactly `value` over the space `id` owns, leaving
+    /// it alone elsewhere. Dangerous: this rewrites history, and is meant
+    /// only for repairing a region known to be corrupt. Fails with
+    /// `ItcError::Monotonicity` if that would lower the event anywhere,
+    /// unless `force` is set.
+    pub fn set_region(&self, id: &Ident, value: i64, force: bool) -> Result<Event, ItcError> {
+        let owned = indicator(id);
+
+        if !force && find_region(self, &owned, |e, o| o == 1 && e > value).is_some() {
+            return Err(ItcError::Monotonicity);
+        }
+
+        zip_with(self, &owned, &|e, o| if o == 1 { value } else { e }).try_norm()
+    }
+
+    /// Like `min`, also returning the leftmost region holding the minimum.
+    pub fn min_region(&self) -> (i64, IdentPath) {
+        extreme_region(self, &|x, y| x < y)
+    }
+
+    /// Like `max`, also returning the leftmost region holding the maximum.
+    pub fn max_region(&self) -> (i64, IdentPath) {
+        extreme_region(self, &|x, y| x > y)
+    }
+}
+
+// Finds the leftmost leaf whose value no other leaf beats.
+fn extreme_region<F>(e: &Event, beats: &F) -> (i64, IdentPath)
+        where F: Fn(i64, i64) -> bool {
+    match *e {
+        Event::Leaf(n) => (n, IdentPath::root()),
+        Event::Node(n, ref l, ref r) => {
+            let (vl, pl) = extreme_region(l, beats);
+            let (vr, pr) = extreme_region(r, beats);
+
+            let (v, side, mut path) = if beats(vr, vl) {
+                (vr, Side::Right, pr)
+            } else {
+                (vl, Side::Left, pl)
+            };
+
+            path.steps.insert(0, side);
+            (n + v, path)
+        },
+    }
+}
+
+/// The event taking 1 where `id` owns the space and 0 elsewhere.
+pub(crate) fn indicator(id: &Ident) -> Event {
+    match *id {
+        Ident::Zero => Event::Leaf(0),
+        Ident::One => Event::Leaf(1),
+        Ident::Tuple(ref l, ref r) => Event::Node(0, Rc::new(indicator(l)), Rc::new(indicator(r))),
+    }
+}
+
+/// The runs of slots, in order, that `id` fully owns when the id space is cut
+/// into `2^depth` equal slots, for `depth` up to 63.
+pub(crate) fn owned_slots(id: &Ident, depth: u32) -> Vec<Range<u64>> {
+    let mut runs: Vec<Range<u64>> = Vec::new();
+
+    for region in id.regions() {
+        if region.depth() > depth as usize {
+            continue;
+        }
+
+        let width = 1u64 << (depth as usize - region.depth());
+        let start = region.steps.iter().fold(0u64, |at, side| match *side {
+            Side::Left => at << 1,
+            Side::Right => (at << 1) | 1,
+        }) * width;
+
+        match runs.last_mut() {
+            Some(run) if run.end == start => run.end += width,
+            _ => runs.push(start..start + width),
+        }
+    }
+
+    runs
+}
+
+/// The normalized id owning exactly `slots` out of `2^depth`, for `depth` up
+/// to 63.
+pub(crate) fn slots_ident(depth: u32, slots: Range<u64>) -> Ident {
+    fn build(width: u64, lo: u64, hi: u64) -> Ident {
+        if lo >= hi {
+            Ident::Zero
+        } else if lo == 0 && hi == width {
+            Ident::One
+        } else {
+            let half = width / 2;
+            Ident::Tuple(
+                Rc::new(build(half, lo, hi.min(half))),
+                Rc::new(build(half, lo.max(half) - half, hi.max(half) - half)),
+            )
+        }
+    }
+
+    build(1u64 << depth, slots.start, slots.end)
+}
+
+/// Visits the coarsest common refinement of two events' trees, calling `f`
+/// with each region and the values `a` and `b` take over it. Stops early and
+/// returns false as soon as `f` does.
+pub(crate) fn for_each_region<F>(a: &Event, b: &Event, f: &mut F) -> bool
+        where F: FnMut(&IdentPath, i64, i64) -> bool {
+    walk(a, 0, b, 0, &mut IdentPath::root(), f)
+}
+
+/// Finds the first region, left to right, where `pred` holds of the values
+/// `a` and `b` take over it.
+pub(crate) fn find_region<P>(a: &Event, b: &Event, pred: P) -> Option<IdentPath>
+        where P: Fn(i64, i64) -> bool {
+    let mut found = None;
+
+    for_each_region(a, b, &mut |path, va, vb| {
+        if pred(va, vb) {
+            found = Some(path.clone());
+            return false;
+        }
+        true
+    });
+
+    found
+}
+
+/// Lists every region, left to right, where `pred` holds of the values `a`
+/// and `b` take over it, with sibling regions merged into their parent.
+pub(crate) fn regions_where<P>(a: &Event, b: &Event, pred: P) -> Vec<IdentPath>
+        where P: Fn(i64, i64) -> bool {
+    let mut found: Vec<IdentPath> = Vec::new();
+
+    for_each_region(a, b, &mut |path, va, vb| {
+        if pred(va, vb) {
+            found.push(path.clone());
+
+            // a right half completes its left sibling if that was found too
+            while found.len() >= 2 {
+                let right = &found[found.len() - 1].steps;
+                let left = &found[found.len() - 2].steps;
+                let n = right.len();
+
+                if n == 0 || left.len() != n || left[..n - 1] != right[..n - 1] ||
+                        left[n - 1] != Side::Left || right[n - 1] != Side::Right {
+                    break;
+                }
+
+                found.pop();
+                found.last_mut().unwrap().pop();
+            }
+        }
+        true
+    });
+
+    found
+}
+
+/// Builds the event taking `f(a, b)` over every region of the common
+/// refinement of `a` and `b`. The result is not normalized.
+pub(crate) fn zip_with<F>(a: &Event, b: &Event, f: &F) -> Event
+        where F: Fn(i64, i64) -> i64 {
+    zip(a, 0, b, 0, f)
+}
+
+fn zip<F>(a: &Event, oa: i64, b: &Event, ob: i64, f: &F) -> Event
+        where F: Fn(i64, i64) -> i64 {
+    if let (&Event::Leaf(n), &Event::Leaf(m)) = (a, b) {
+        return Event::Leaf(f(oa + n, ob + m));
+    }
+
+    let (al, ar, oa) = halves(a, oa);
+    let (bl, br, ob) = halves(b, ob);
+
+    Event::Node(0, Rc::new(zip(al, oa, bl, ob, f)), Rc::new(zip(ar, oa, br, ob, f)))
+}
+
+fn walk<F>(a: &Event, oa: i64, b: &Event, ob: i64, path: &mut IdentPath, f: &mut F)
+        -> bool where F: FnMut(&IdentPath, i64, i64) -> bool {
+    if let (&Event::Leaf(n), &Event::Leaf(m)) = (a, b) {
+        return f(path, oa + n, ob + m);
+    }
+
+    let (al, ar, oa) = halves(a, oa);
+    let (bl, br, ob) = halves(b, ob);
+
+    path.push(Side::Left);
+    let more = walk(al, oa, bl, ob, path, f);
+    path.pop();
+
+    if !more {
+        return false;
+    }
+
+    path.push(Side::Right);
+    let more = walk(ar, oa, br, ob, path, f);
+    path.pop();
+
+    more
+}
+
+// A leaf covers both halves of its region with the same value.
+fn halves(e: &Event, offset: i64) -> (&Event, &Event, i64) {
+    match *e {
+        Event::Leaf(_) => (e, e, offset),
+        Event::Node(n, ref l, ref r) => (l, r, offset + n),
+    }
+}
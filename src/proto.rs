@@ -0,0 +1,114 @@
+//! Protobuf messages for ids, events and stamps.
+//!
+//! The messages follow `proto/itc.proto`, so stamps can travel in gRPC services
+//! alongside other protobuf types. Trees are nested messages, one per node, so
+//! prost's own recursion limit of 100 caps the depth of decodable trees. As
+//! with the binary encoding, events are always brought into normal form when
+//! read.
+//!
+//! The messages are written out by hand rather than generated by
+//! `prost-build`, which would need `protoc` wherever the crate is built; the
+//! protobuf tests pin their wire format to the schema.
+
+use std::rc::Rc;
+
+use prost::Message;
+
+use DecodeError;
+use encoding::normal_node;
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Ident {
+    #[prost(bool, tag = "1")]
+    pub one: bool,
+    #[prost(message, optional, boxed, tag = "2")]
+    pub left: Option<Box<Ident>>,
+    #[prost(message, optional, boxed, tag = "3")]
+    pub right: Option<Box<Ident>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Event {
+    #[prost(sint64, tag = "1")]
+    pub value: i64,
+    #[prost(message, optional, boxed, tag = "2")]
+    pub left: Option<Box<Event>>,
+    #[prost(message, optional, boxed, tag = "3")]
+    pub right: Option<Box<Event>>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct Stamp {
+    #[prost(message, optional, tag = "1")]
+    pub id: Option<Ident>,
+    #[prost(message, optional, tag = "2")]
+    pub event: Option<Event>,
+}
+
+impl ::Ident {
+    pub fn to_proto(&self) -> Ident {
+        match *self {
+            ::Ident::Zero => Ident { one: false, left: None, right: None },
+            ::Ident::One => Ident { one: true, left: None, right: None },
+            ::Ident::Tuple(ref l, ref r) => Ident {
+                one: false,
+                left: Some(Box::new(l.to_proto())),
+                right: Some(Box::new(r.to_proto())),
+            },
+        }
+    }
+
+    pub fn from_proto(msg: &Ident) -> Result<::Ident, DecodeError> {
+        match (msg.one, &msg.left, &msg.right) {
+            (false, None, None) => Ok(::Ident::Zero),
+            (true, None, None) => Ok(::Ident::One),
+            (false, Some(l), Some(r)) => Ok(::Ident::Tuple(
+                Rc::new(::Ident::from_proto(l)?),
+                Rc::new(::Ident::from_proto(r)?),
+            )),
+            _ => Err(DecodeError::Malformed),
+        }
+    }
+}
+
+impl ::Event {
+    pub fn to_proto(&self) -> Event {
+        match *self {
+            ::Event::Leaf(n) => Event { value: n, left: None, right: None },
+            ::Event::Node(n, ref l, ref r) => Event {
+                value: n,
+                left: Some(Box::new(l.to_proto())),
+                right: Some(Box::new(r.to_proto())),
+            },
+        }
+    }
+
+    pub fn from_proto(msg: &Event) -> Result<::Event, DecodeError> {
+        match (&msg.left, &msg.right) {
+            (None, None) => Ok(::Event::Leaf(msg.value)),
+            (Some(l), Some(r)) => normal_node(
+                msg.value,
+                Rc::new(::Event::from_proto(l)?),
+                Rc::new(::Event::from_proto(r)?),
+            ),
+            _ => Err(DecodeError::Malformed),
+        }
+    }
+}
+
+impl ::Stamp {
+    pub fn to_proto(&self) -> Stamp {
+        Stamp {
+            id: Some(self.id.to_proto()),
+            event: Some(self.event.to_proto()),
+        }
+    }
+
+    pub fn from_proto(msg: &Stamp) -> Result<::Stamp, DecodeError> {
+        match (&msg.id, &msg.event) {
+            (Some(id), Some(event)) =>
+                Ok(::Stamp::new(::Ident::from_proto(id)?, ::Event::from_proto(event)?)),
+            _ => Err(DecodeError::Malformed),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Naming nodes by stable external UUIDs.
+//!
+//! Applications usually know their nodes by some fixed identity, while ITC ids
+//! change shape as nodes come and go. A `NodeRegistry` records which id each
+//! node currently owns, and keeps that record up to date as nodes fork off new
+//! members and hand their ids back on departure.
+
+use std::collections::btree_map;
+use std::collections::BTreeMap;
+
+use Ident;
+use ItcError;
+use Stamp;
+
+#[derive(Clone, Debug)]
+pub struct NodeStamp {
+    pub node: u128,
+    pub stamp: Stamp,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct NodeRegistry {
+    ids: BTreeMap<u128, Ident>,
+}
+
+impl NodeRegistry {
+    pub fn new() -> NodeRegistry {
+        NodeRegistry { ids: BTreeMap::new() }
+    }
+
+    /// Starts a cluster whose only member, `node`, holds the seed stamp.
+    pub fn seed(&mut self, node: u128) -> Result<NodeStamp, ItcError> {
+        if self.ids.contains_key(&node) {
+            return Err(ItcError::DuplicateNode(node));
+        }
+
+        let stamp = Stamp::seed();
+        self.ids.insert(node, stamp.id.clone());
+        Ok(NodeStamp { node, stamp })
+    }
+
+    /// Admits `node` to the cluster by forking `parent`'s stamp.
+    pub fn fork(&mut self, parent: &mut NodeStamp, node: u128)
+            -> Result<NodeStamp, ItcError> {
+        if !self.ids.contains_key(&parent.node) {
+            return Err(ItcError::UnknownNode(parent.node));
+        }
+
+        if self.ids.contains_key(&node) {
+            return Err(ItcError::DuplicateNode(node));
+        }
+
+        let (kept, given) = parent.stamp.fork();
+        parent.stamp = kept;
+        self.ids.insert(parent.node, parent.stamp.id.clone());
+        self.ids.insert(node, given.id.clone());
+        Ok(NodeStamp { node, stamp: given })
+    }
+
+    /// Removes `departing` from the cluster, handing its id and history to
+    /// `heir`.
+    pub fn reclaim(&mut self, heir: &mut NodeStamp, departing: NodeStamp)
+            -> Result<(), ItcError> {
+        if !self.ids.contains_key(&heir.node) {
+            return Err(ItcError::UnknownNode(heir.node));
+        }
+
+        if self.ids.remove(&departing.node).is_none() {
+            return Err(ItcError::UnknownNode(departing.node));
+        }
+
+        heir.stamp = heir.stamp.join(&departing.stamp);
+        self.ids.insert(heir.node, heir.stamp.id.clone());
+        Ok(())
+    }
+
+    pub fn ident(&self, node: u128) -> Option<&Ident> {
+        self.ids.get(&node)
+    }
+
+    pub fn contains(&self, node: u128) -> bool {
+        self.ids.contains_key(&node)
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Formats a node UUID the usual way, as in
+    /// `67e55044-10b1-426f-9247-bb680e5fe0c8`.
+    pub fn node_name(node: u128) -> String {
+        format!("{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+                node >> 96,
+                (node >> 80) & 0xffff,
+                (node >> 64) & 0xffff,
+                (node >> 48) & 0xffff,
+                node & 0xffff_ffff_ffff)
+    }
+
+    pub fn nodes(&self) -> btree_map::Iter<'_, u128, Ident> {
+        self.ids.iter()
+    }
+}
@@ -0,0 +1,103 @@
+//! Events as a base value plus exceptions.
+//!
+//! Clocks in a steady state tend to take the same value over most of the id
+//! space. A `SparseEvent` stores that value once, and lists only the leaf
+//! regions of the normalized tree that differ from it, left to right, each
+//! with its offset from the base. The base is the value covering the largest
+//! share of the id space, the smallest such value on ties. Comparisons and
+//! joins go through the tree form.
+
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use Event;
+use IdentPath;
+use Side;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SparseEvent {
+    base: i64,
+    exceptions: Vec<(IdentPath, i64)>,
+}
+
+impl SparseEvent {
+    pub fn base(&self) -> i64 {
+        self.base
+    }
+
+    pub fn exceptions(&self) -> &[(IdentPath, i64)] {
+        &self.exceptions
+    }
+
+    /// Rebuilds the normalized tree.
+    pub fn to_event(&self) -> Event {
+        let mut e = Event::Leaf(self.base);
+
+        for &(ref region, delta) in &self.exceptions {
+            e = set_region(&e, region.steps(), self.base + delta);
+        }
+
+        e.norm()
+    }
+
+    pub fn leq(&self, other: &SparseEvent) -> bool {
+        self.to_event().leq(&other.to_event())
+    }
+
+    pub fn causal_cmp(&self, other: &SparseEvent) -> Option<Ordering> {
+        match (self.leq(other), other.leq(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+
+    pub fn join(&self, other: &SparseEvent) -> SparseEvent {
+        self.to_event().join(&other.to_event()).to_sparse()
+    }
+}
+
+impl Event {
+    pub fn to_sparse(&self) -> SparseEvent {
+        let leaves = self.norm().leaf_values();
+
+        let mut shares: Vec<(i64, f64)> = Vec::new();
+        for &(ref region, value) in &leaves {
+            let share = 0.5f64.powi(region.depth() as i32);
+            match shares.iter_mut().find(|s| s.0 == value) {
+                Some(s) => s.1 += share,
+                None => shares.push((value, share)),
+            }
+        }
+
+        let base = shares.iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map_or(0, |s| s.0);
+
+        let exceptions = leaves.into_iter()
+            .filter(|&(_, value)| value != base)
+            .map(|(region, value)| (region, value - base))
+            .collect();
+
+        SparseEvent { base, exceptions }
+    }
+}
+
+// Sets the absolute value over a region, splitting leaves on the way down.
+fn set_region(e: &Event, steps: &[Side], value: i64) -> Event {
+    let (side, rest) = match steps.split_first() {
+        Some(split) => split,
+        None => return Event::Leaf(value),
+    };
+
+    let (n, l, r) = match *e {
+        Event::Leaf(n) => (0, Rc::new(Event::Leaf(n)), Rc::new(Event::Leaf(n))),
+        Event::Node(n, ref l, ref r) => (n, l.clone(), r.clone()),
+    };
+
+    match *side {
+        Side::Left => Event::Node(n, Rc::new(set_region(&l, rest, value - n)), r),
+        Side::Right => Event::Node(n, l, Rc::new(set_region(&r, rest, value - n))),
+    }
+}
@@ -0,0 +1,556 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
+
+use Event;
+use Ident;
+use IdentPath;
+use ItcError;
+use NodeRegistry;
+use digest;
+use path;
+
+/// Rules for deciding when a stamp's event tree has grown enough to be worth
+/// shrinking with `Stamp::shrink`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompactPolicy {
+    MaxDepth(usize),
+    MaxSize(usize),
+    /// Depth divided by the depth of a balanced tree with as many leaves.
+    MaxDepthRatio(f64),
+}
+
+/// A mailbox sorted by `Stamp::partition_deliverable`. Each list keeps the
+/// mailbox's order.
+#[derive(Debug)]
+pub struct Deliveries<'a, T: 'a> {
+    /// Messages that can be delivered now.
+    pub deliverable: Vec<&'a (Stamp, T)>,
+    /// Messages that must wait for others to be delivered first.
+    pub waiting: Vec<&'a (Stamp, T)>,
+    /// Messages whose events this stamp has already seen.
+    pub seen: Vec<&'a (Stamp, T)>,
+    /// Unseen messages sent with an anonymous id, which cannot say what a
+    /// single tick of their sender covers and so could never be delivered.
+    pub anonymous: Vec<&'a (Stamp, T)>,
+}
+
+/// An id paired with the event it has observed, as in the paper's (i, e).
+///
+/// Trees share their nodes through `Rc`, so a stamp is neither `Send` nor
+/// `Sync`, and values are always `i64`; there is deliberately no version
+/// generic over the counter or the pointer, which would have to thread both
+/// through every tree operation. To hand a stamp to another thread, send its
+/// `encode`d bytes and `decode` them there.
+#[derive(Clone, Debug)]
+pub struct Stamp {
+    pub id: Ident,
+    pub event: Event,
+}
+
+/// A stamp tagged with a lineage label by `Stamp::seed_from`. The label is
+/// carried through forks, ticks and joins but takes no part in causality and
+/// is not encoded.
+#[derive(Clone, Debug)]
+pub struct LabeledStamp {
+    pub stamp: Stamp,
+    pub label: [u8; 16],
+}
+
+impl LabeledStamp {
+    pub fn fork(&self) -> (LabeledStamp, LabeledStamp) {
+        let (s1, s2) = self.stamp.fork();
+        (self.relabel(s1), self.relabel(s2))
+    }
+
+    pub fn event(&self) -> LabeledStamp {
+        self.relabel(self.stamp.event())
+    }
+
+    /// Joins as `Stamp::join` does, keeping this stamp's label.
+    pub fn join(&self, other: &Stamp) -> LabeledStamp {
+        self.relabel(self.stamp.join(other))
+    }
+
+    /// Whether the stamps are identical and carry the same label.
+    pub fn identical(&self, other: &LabeledStamp) -> bool {
+        self.label == other.label && self.stamp.identical(&other.stamp)
+    }
+
+    fn relabel(&self, stamp: Stamp) -> LabeledStamp {
+        LabeledStamp { stamp, label: self.label }
+    }
+}
+
+/// An opaque token ordering stamps as `total_cmp` does, for storing next to
+/// records in place of the stamp. Its bytes sort in the same order.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CausalTimestamp(Vec<u8>);
+
+impl CausalTimestamp {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Wraps bytes previously taken from `as_bytes`.
+    pub fn from_bytes(bytes: Vec<u8>) -> CausalTimestamp {
+        CausalTimestamp(bytes)
+    }
+}
+
+impl Stamp {
+    pub fn new(id: Ident, event: Event) -> Stamp {
+        Stamp { id, event }
+    }
+
+    pub fn seed() -> Stamp {
+        Stamp::new(Ident::seed(), Event::seed())
+    }
+
+    /// Creates a seed stamp tagged with a label derived from `label`.
+    pub fn seed_from(label: &[u8]) -> LabeledStamp {
+        LabeledStamp { stamp: Stamp::seed(), label: digest::fnv1a(label).to_be_bytes() }
+    }
+
+    pub fn fork(&self) -> (Stamp, Stamp) {
+        let (i1, i2) = self.id.split();
+        let (s1, s2) = (self.derive(i1, self.event.clone()), self.derive(i2, self.event.clone()));
+        s1.debug_assert_valid_after(&[self]);
+        s2.debug_assert_valid_after(&[self]);
+        (s1, s2)
+    }
+
+    /// Forks into `shard_count` stamps, handing each a run of consecutive
+    /// slots of the id space: the id space is cut into the fewest equal slots
+    /// such that this id owns whole slots, at least `shard_count` of them, and
+    /// the owned slots are dealt out in order, as evenly as possible. Shards
+    /// left over when the id is too thin get `Zero` ids.
+    pub fn fork_shards(&self, shard_count: usize) -> Vec<Stamp> {
+        let id = self.id.canonical();
+        let depth = (id.depth().min(63) as u32..64)
+            .find(|&d| id.fork_capacity(d as usize) >= shard_count as u64)
+            .unwrap_or(63);
+        let runs = path::owned_slots(&id, depth);
+        let owned: u64 = runs.iter().map(|r| r.end - r.start).sum();
+
+        (0..shard_count).map(|i| {
+            let lo = (owned as u128 * i as u128 / shard_count as u128) as u64;
+            let hi = (owned as u128 * (i + 1) as u128 / shard_count as u128) as u64;
+            self.derive(shard_ident(&runs, depth, lo..hi), self.event.clone())
+        }).collect()
+    }
+
+    /// Forks after bringing the id into canonical form and the event into
+    /// normal form, so that neither half inherits a bloated copy of a stamp
+    /// built or assigned by hand. For stamps this crate produced, which are
+    /// already compact, it is the same as `fork`.
+    pub fn fork_compact(&self) -> (Stamp, Stamp) {
+        self.derive(self.id.canonical(), self.event.norm()).fork()
+    }
+
+    pub fn event(&self) -> Stamp {
+        let next = self.derive(self.id.clone(), self.event.event(&self.id));
+        next.debug_assert_valid_after(&[self]);
+        next
+    }
+
+    /// Joins two stamps. The summed id is brought into canonical form, so
+    /// sibling halves of a fork merge back into their parent.
+    pub fn join(&self, other: &Stamp) -> Stamp {
+        let joined = Stamp {
+            id: self.id.sum(&other.id).canonical(),
+            event: self.event.join(&other.event),
+        };
+        joined.debug_assert_valid_after(&[self, other]);
+        joined
+    }
+
+    /// Joins `incoming` only if it is a pure catch-up, that is if it
+    /// dominates this stamp or this stamp already dominates it. Fails with
+    /// `ItcError::Concurrent`, leaving the stamp as it was, otherwise.
+    pub fn fast_forward(&mut self, incoming: &Stamp) -> Result<(), ItcError> {
+        if self.concurrent(incoming) {
+            return Err(ItcError::Concurrent);
+        }
+
+        *self = self.join(incoming);
+        Ok(())
+    }
+
+    /// Joins `incoming` into this stamp, returning the largest regions whose
+    /// counts it advanced, left to right.
+    pub fn receive_learning(&mut self, incoming: &Stamp) -> Vec<IdentPath> {
+        let learned = path::regions_where(&self.event, &incoming.event, |mine, theirs| {
+            theirs > mine
+        });
+        *self = self.join(incoming);
+        learned
+    }
+
+    /// Sorts a mailbox into the messages this stamp can deliver now, those
+    /// that must wait, those it has already seen and those it could never
+    /// deliver. Every message carries its sender's stamp, id included, as of
+    /// the event that sent it. A message waits while another undelivered one
+    /// in the mailbox causally precedes it, or while its event is further
+    /// ahead of this one than a single tick with the sender's id could take
+    /// it.
+    pub fn partition_deliverable<'a, T>(&self, msgs: &'a [(Stamp, T)]) -> Deliveries<'a, T> {
+        let mut out = Deliveries {
+            deliverable: Vec::new(),
+            waiting: Vec::new(),
+            seen: Vec::new(),
+            anonymous: Vec::new(),
+        };
+        let pending: Vec<&Stamp> = msgs.iter()
+            .map(|(sent, _)| sent)
+            .filter(|sent| !sent.event.leq(&self.event) && !sent.id.is_zero())
+            .collect();
+
+        for msg in msgs {
+            let sent = &msg.0;
+
+            if sent.event.leq(&self.event) {
+                out.seen.push(msg);
+            } else if sent.id.is_zero() {
+                out.anonymous.push(msg);
+            } else {
+                let bumped = path::zip_with(&self.event, &path::indicator(&sent.id), &|e, owned| e + owned);
+                let reachable = bumped.join(&self.event.event(&sent.id));
+                let preceded = pending.iter().any(|earlier| earlier.event.leq(&sent.event) &&
+                    !sent.event.leq(&earlier.event));
+
+                if sent.event.leq(&reachable) && !preceded {
+                    out.deliverable.push(msg);
+                } else {
+                    out.waiting.push(msg);
+                }
+            }
+        }
+
+        out
+    }
+
+    /// Returns an anonymous copy of this stamp, suitable for sending in a
+    /// message.
+    pub fn peek(&self) -> Stamp {
+        self.derive(Ident::Zero, self.event.clone())
+    }
+
+    /// Returns the stamp with its event in normal form, the smallest tree
+    /// describing the same causal history.
+    pub fn compact(&self) -> Stamp {
+        self.derive(self.id.clone(), self.event.norm())
+    }
+
+    /// Whether the event breaks `threshold`. Events this crate produces are
+    /// already compact, so the remedy is `shrink` rather than `compact`.
+    pub fn should_compact(&self, threshold: CompactPolicy) -> bool {
+        breaks(&self.event, threshold)
+    }
+
+    /// Flattens the event, as `Event::join_capped` does, at the greatest depth
+    /// at which it no longer breaks `threshold`. Unlike `compact` this loses
+    /// precision: each flattened region claims the highest count seen in it,
+    /// so the result dominates this stamp. A `MaxSize` below one can never be
+    /// met, and gives a single leaf.
+    pub fn shrink(&self, threshold: CompactPolicy) -> Stamp {
+        let mut event = self.event.norm();
+
+        for depth in (0..event.depth()).rev() {
+            if !breaks(&event, threshold) {
+                break;
+            }
+            event = event.join_capped(&event, depth);
+        }
+
+        self.derive(self.id.clone(), event)
+    }
+
+    /// Lists, for every region owned by a registered node, the node's name and
+    /// the highest count this stamp has seen from that region.
+    pub fn annotate_regions(&self, node_names: &NodeRegistry) -> Vec<(String, i64)> {
+        let leaves = self.event.leaf_values();
+        let mut out = Vec::new();
+
+        for (&node, id) in node_names.nodes() {
+            for region in id.regions() {
+                let value = leaves.iter()
+                    .filter(|&(path, _)| path.overlaps(&region))
+                    .map(|&(_, v)| v)
+                    .max()
+                    .unwrap_or(0);
+                out.push((NodeRegistry::node_name(node), value));
+            }
+        }
+
+        out
+    }
+
+    /// Projects the event onto a vector-clock-shaped map, giving each
+    /// registered node the highest count seen from any of its regions.
+    pub fn to_map(&self, registry: &NodeRegistry) -> HashMap<String, u64> {
+        let leaves = self.event.leaf_values();
+
+        registry.nodes().map(|(&node, id)| {
+            let regions = id.regions();
+            let value = leaves.iter()
+                .filter(|&(path, _)| regions.iter().any(|r| path.overlaps(r)))
+                .map(|&(_, v)| v)
+                .max()
+                .unwrap_or(0);
+            (NodeRegistry::node_name(node), value.max(0) as u64)
+        }).collect()
+    }
+
+    /// Whether the two stamps are the same up to swapping the halves of their
+    /// id trees: the ids must be mirror images of each other at any number of
+    /// levels, with the events mirrored along with them.
+    pub fn topologically_equal(a: &Stamp, b: &Stamp) -> bool {
+        mirrored(&a.id.canonical(), &a.event, &b.id.canonical(), &b.event)
+    }
+
+    /// Events per second between two snapshots of a clock, summing the
+    /// increment over each region the two events' trees cut the id space
+    /// into. Neighbouring regions that advanced alike still count once each.
+    /// Returns zero if no time has passed.
+    pub fn event_rate(old: &Stamp, new: &Stamp, elapsed_secs: f64) -> f64 {
+        if elapsed_secs <= 0.0 {
+            return 0.0;
+        }
+
+        let increments = path::zip_with(&new.event, &old.event, &|n, o| {
+            std::cmp::max(n - o, 0)
+        });
+
+        let total: i64 = increments.leaf_values().iter().map(|&(_, v)| v).sum();
+        total as f64 / elapsed_secs
+    }
+
+    /// A heuristic for progress bars: how far, from 0 to 100, this stamp has
+    /// caught up with `target`. Each region counts in proportion to its width,
+    /// and contributes the fraction of the target's count seen there. Only a
+    /// stamp dominating `target` scores 100.
+    pub fn catch_up_percent(&self, target: &Event) -> f64 {
+        let mut caught_up = 0.0;
+
+        path::for_each_region(&self.event, target, &mut |region, mine, theirs| {
+            let fraction = if mine >= theirs {
+                1.0
+            } else if mine <= 0 {
+                0.0
+            } else {
+                mine as f64 / theirs as f64
+            };
+
+            caught_up += fraction * 0.5f64.powi(region.depth() as i32);
+            true
+        });
+
+        // Tiny regions can vanish in the rounding.
+        if !target.leq(&self.event) {
+            caught_up = caught_up.min(1.0 - f64::EPSILON);
+        }
+
+        100.0 * caught_up
+    }
+
+    pub fn leq(&self, other: &Stamp) -> bool {
+        self.event.leq(&other.event)
+    }
+
+    pub fn concurrent(&self, other: &Stamp) -> bool {
+        !self.leq(other) && !other.leq(self)
+    }
+
+    /// Orders stamps by the events they have seen, `None` when they are
+    /// concurrent. This is also the `PartialOrd` order.
+    pub fn causal_cmp(&self, other: &Stamp) -> Option<Ordering> {
+        match (self.leq(other), other.leq(self)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        }
+    }
+
+    /// A total order extending the causal one: stamps are ordered by the
+    /// integral of their event over the id space, then by the digests of
+    /// their events. Concurrent stamps are ordered arbitrarily but
+    /// consistently. Events deeper than 56 levels are compared as if
+    /// flattened to that depth, where the order may break ties against
+    /// causality.
+    pub fn total_cmp(&self, other: &Stamp) -> Ordering {
+        integral(&self.event, 0, INTEGRAL_DEPTH)
+            .cmp(&integral(&other.event, 0, INTEGRAL_DEPTH))
+            .then_with(|| self.event.digest().cmp(&other.event.digest()))
+    }
+
+    /// Captures where the stamp falls in the `total_cmp` order in 32 bytes,
+    /// however large the stamp: the integral of its event, big-endian with
+    /// the sign bit flipped so that bytes compare as numbers, then the
+    /// event's digest.
+    pub fn as_causal_timestamp(&self) -> CausalTimestamp {
+        let key = integral(&self.event, 0, INTEGRAL_DEPTH) ^ i128::MIN;
+        let mut bytes = key.to_be_bytes().to_vec();
+        bytes.extend(&self.event.digest());
+        CausalTimestamp(bytes)
+    }
+
+    /// Whether the stamps are the same in every respect: ids equal in
+    /// canonical form, and equal events.
+    pub fn identical(&self, other: &Stamp) -> bool {
+        self.id.canonical() == other.id.canonical() && self.event == other.event
+    }
+
+    /// For concurrent stamps, returns a region where `self` has seen more
+    /// events than `other`, and one where `other` has seen more than `self`.
+    /// Returns `None` when the stamps are comparable.
+    pub fn concurrency_witness(&self, other: &Stamp)
+            -> Option<(IdentPath, IdentPath)> {
+        let ahead = path::find_region(&self.event, &other.event, |a, b| a > b)?;
+        let behind = path::find_region(&self.event, &other.event, |a, b| a < b)?;
+        Some((ahead, behind))
+    }
+
+    /// Panics, in debug builds only, unless the id is in canonical form and
+    /// the event in normal form with no count below zero. The id and event
+    /// trees need no common shape, as either may be finer than the other
+    /// anywhere, so the check is on each alone. Every operation leaves valid
+    /// stamps valid, and checks that it did.
+    pub fn debug_assert_valid(&self) {
+        if cfg!(debug_assertions) {
+            assert!(self.id == self.id.canonical(), "stamp id is not canonical: {:?}", self.id);
+            assert!(self.event.is_normal(), "stamp event is not normalized: {:?}", self.event);
+            assert!(self.event.min() >= 0, "stamp event counts below zero: {:?}", self.event);
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        self.id == self.id.canonical() && self.event.is_normal() && self.event.min() >= 0
+    }
+
+    // Checks the result of an operation, unless its inputs were already bad.
+    fn debug_assert_valid_after(&self, inputs: &[&Stamp]) {
+        if cfg!(debug_assertions) && inputs.iter().all(|s| s.is_valid()) {
+            self.debug_assert_valid();
+        }
+    }
+
+    fn derive(&self, id: Ident, event: Event) -> Stamp {
+        Stamp { id, event }
+    }
+}
+
+fn breaks(event: &Event, threshold: CompactPolicy) -> bool {
+    match threshold {
+        CompactPolicy::MaxDepth(depth) => event.depth() > depth,
+        CompactPolicy::MaxSize(size) => event.size() > size,
+        CompactPolicy::MaxDepthRatio(ratio) => {
+            let leaves = event.size().div_ceil(2);
+            let ideal = leaves.next_power_of_two().trailing_zeros().max(1);
+            event.depth() as f64 / ideal as f64 > ratio
+        },
+    }
+}
+
+// The id owning the owned slots numbered `picked`, counting through `runs`.
+fn shard_ident(runs: &[Range<u64>], depth: u32, picked: Range<u64>) -> Ident {
+    let mut id = Ident::Zero;
+    let mut skipped = 0;
+
+    for run in runs {
+        let len = run.end - run.start;
+        let lo = picked.start.clamp(skipped, skipped + len) - skipped;
+        let hi = picked.end.clamp(skipped, skipped + len) - skipped;
+
+        if lo < hi {
+            id = id.sum(&path::slots_ident(depth, run.start + lo..run.start + hi));
+        }
+
+        skipped += len;
+    }
+
+    id
+}
+
+/// Stamps are equal when they have seen the same events, whatever their ids.
+impl PartialEq for Stamp {
+    fn eq(&self, other: &Stamp) -> bool {
+        self.event == other.event
+    }
+}
+
+impl PartialOrd for Stamp {
+    fn partial_cmp(&self, other: &Stamp) -> Option<Ordering> {
+        self.causal_cmp(other)
+    }
+}
+
+// Deep enough for any real clock, shallow enough that `integral` fits in an
+// i128: absolute values stay below 2^69, and weights sum to 2^56.
+const INTEGRAL_DEPTH: u32 = 56;
+
+// The integral of `e`, lifted by `base`, over a region of width `2^depth`.
+// Subtrees too deep to resolve count as their maximum.
+fn integral(e: &Event, base: i128, depth: u32) -> i128 {
+    match *e {
+        Event::Leaf(n) => (base + n as i128) << depth,
+        Event::Node(..) if depth == 0 => base + e.max() as i128,
+        Event::Node(n, ref l, ref r) =>
+            integral(l, base + n as i128, depth - 1) + integral(r, base + n as i128, depth - 1),
+    }
+}
+
+/// A one-line summary for logs: the share of the id space the stamp owns, as
+/// a fraction, and the event's extrema and size.
+impl fmt::Display for Stamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "id=")?;
+        write_share(f, &self.id)?;
+        write!(f, " ev[min={} max={} size={}]", self.event.min(), self.event.max(), self.event.size())
+    }
+}
+
+fn write_share(f: &mut fmt::Formatter, id: &Ident) -> fmt::Result {
+    let regions = id.regions();
+    let depth = regions.iter().map(|r| r.depth()).max().unwrap_or(0);
+
+    if depth >= 128 {
+        let share: f64 = regions.iter().map(|r| 0.5f64.powi(r.depth() as i32)).sum();
+        return write!(f, "{}", share);
+    }
+
+    let num: u128 = regions.iter().map(|r| 1u128 << (depth - r.depth())).sum();
+    let common = std::cmp::min(num.trailing_zeros() as usize, depth);
+
+    match (num >> common, depth - common) {
+        (0, _) => write!(f, "0"),
+        (n, 0) => write!(f, "{}", n),
+        (n, d) => write!(f, "{}/{}", n, 1u128 << d),
+    }
+}
+
+fn mirrored(ia: &Ident, ea: &Event, ib: &Ident, eb: &Event) -> bool {
+    match (ia, ib) {
+        (Ident::Tuple(al, ar), Ident::Tuple(bl, br)) => {
+            let (eal, ear) = halves(ea);
+            let (ebl, ebr) = halves(eb);
+
+            (mirrored(al, &eal, bl, &ebl) && mirrored(ar, &ear, br, &ebr)) ||
+            (mirrored(al, &eal, br, &ebr) && mirrored(ar, &ear, bl, &ebl))
+        },
+
+        (&Ident::Tuple(..), _) | (_, &Ident::Tuple(..)) => false,
+
+        _ => ia == ib && ea == eb,
+    }
+}
+
+// The events over each half of the region covered by `e`.
+fn halves(e: &Event) -> (Event, Event) {
+    match *e {
+        Event::Leaf(n) => (Event::Leaf(n), Event::Leaf(n)),
+        Event::Node(n, ref l, ref r) => ((**l).clone().lift(n), (**r).clone().lift(n)),
+    }
+}
@@ -0,0 +1,77 @@
+//! A deterministic total order on ids and events, for keys in sorted maps and
+//! storage.
+//!
+//! THIS ORDER IS STRUCTURAL, NOT CAUSAL. It compares canonical encodings byte by
+//! byte, so it says nothing about which of two events happened before the
+//! other; use `Event::leq` for that. It is consistent with equality, though:
+//! `Structural::from(a) == Structural::from(b)` exactly when `a == b` for
+//! events, or when the ids own the same part of the id space.
+//!
+//! The canonical encoding is computed once, when the value is wrapped, so
+//! comparisons in a sorted map only compare bytes.
+
+use std::cmp::Ordering;
+use std::hash::{Hash, Hasher};
+
+use Event;
+use Ident;
+
+#[derive(Clone, Debug)]
+pub struct Structural<T> {
+    value: T,
+    key: Vec<u8>,
+}
+
+impl<T> Structural<T> {
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+
+    fn key(&self) -> &[u8] {
+        &self.key
+    }
+}
+
+macro_rules! structural_order {
+    ($t:ty, $canonical:expr) => {
+        impl From<$t> for Structural<$t> {
+            fn from(value: $t) -> Structural<$t> {
+                let canonical: fn(&$t) -> Vec<u8> = $canonical;
+                Structural { key: canonical(&value), value }
+            }
+        }
+
+        impl PartialEq for Structural<$t> {
+            fn eq(&self, other: &Self) -> bool {
+                self.key() == other.key()
+            }
+        }
+
+        impl Eq for Structural<$t> {}
+
+        impl PartialOrd for Structural<$t> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Structural<$t> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.key().cmp(other.key())
+            }
+        }
+
+        impl Hash for Structural<$t> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.key().hash(state)
+            }
+        }
+    }
+}
+
+structural_order!(Event, |e| e.norm().encode());
+structural_order!(Ident, |i| i.canonical().encode());
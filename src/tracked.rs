@@ -0,0 +1,56 @@
+//! Events that keep their extrema at hand.
+//!
+//! Finding an event's maximum walks the whole tree. A `TrackedEvent` keeps its
+//! event in normal form, where the minimum is the root's value, and caches the
+//! maximum, updating it from what each tick or join changed instead of
+//! walking the tree again.
+
+use Event;
+use Ident;
+
+#[derive(Clone, Debug)]
+pub struct TrackedEvent {
+    event: Event,
+    max: i64,
+}
+
+impl TrackedEvent {
+    pub fn new(event: &Event) -> TrackedEvent {
+        let event = event.norm();
+        let max = event.max();
+        TrackedEvent { event, max }
+    }
+
+    pub fn event(&self) -> &Event {
+        &self.event
+    }
+
+    pub fn into_event(self) -> Event {
+        self.event
+    }
+
+    pub fn min(&self) -> i64 {
+        self.event.value()
+    }
+
+    pub fn max(&self) -> i64 {
+        self.max
+    }
+
+    /// Ticks as `Event::event` does. Filling never raises the maximum, and
+    /// growing raises it at most to the grown leaf's new value.
+    pub fn tick(&mut self, id: &Ident) {
+        let (event, grown) = self.event.event_reporting(id);
+
+        if let Some(v) = grown {
+            self.max = std::cmp::max(self.max, v);
+        }
+
+        self.event = event;
+    }
+
+    pub fn join(&mut self, other: &TrackedEvent) {
+        self.event = self.event.join(&other.event);
+        self.max = std::cmp::max(self.max, other.max);
+    }
+}
@@ -0,0 +1,98 @@
+//! Audits of recorded membership histories and of live ids.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, ForkOp, Ident, Stamp, fork_fairness, id_space_report};
+
+use common::{Rng, owns, random_event, random_ident};
+
+// Forks every member in turn, doubling the cluster `rounds` times.
+fn doubling(rounds: u32) -> Vec<ForkOp> {
+    (0..rounds).flat_map(|r| (0..1 << r).map(ForkOp::Fork)).collect()
+}
+
+#[test]
+fn even_forking_is_fair() {
+    for rounds in 0..6 {
+        let report = fork_fairness(&doubling(rounds));
+        assert_eq!(report.shares, vec![0.5f64.powi(rounds as i32); 1 << rounds]);
+        assert_eq!(report.variance, 0.0);
+        assert_eq!(report.imbalance, 1.0);
+        assert_eq!(report.max_depth, rounds as usize);
+        assert!(!report.is_unfair(1.5));
+    }
+}
+
+#[test]
+fn forking_the_newest_member_is_flagged() {
+    let ops: Vec<ForkOp> = (0..6).map(ForkOp::Fork).collect();
+    let report = fork_fairness(&ops);
+    assert_eq!(report.imbalance, 32.0);
+    assert_eq!(report.max_depth, 6);
+    assert!(report.variance > 0.0);
+    assert!(report.is_unfair(2.0));
+    assert!(!report.is_unfair(32.0));
+}
+
+#[test]
+fn shares_always_cover_the_id_space() {
+    let mut rng = Rng(244);
+
+    for _ in 0..200 {
+        let mut live = vec![0];
+        let mut ops = Vec::new();
+
+        for _ in 0..30 {
+            let k = rng.below(live.len() as u64) as usize;
+            if live.len() > 1 && rng.below(3) == 0 {
+                let departing = live.swap_remove(k);
+                let heir = live[rng.below(live.len() as u64) as usize];
+                ops.push(ForkOp::Join(heir, departing));
+            } else {
+                let created = ops.iter().filter(|op| matches!(op, ForkOp::Fork(_))).count() + 1;
+                ops.push(ForkOp::Fork(live[k]));
+                live.push(created);
+            }
+        }
+
+        let report = fork_fairness(&ops);
+        assert_eq!(report.shares.len(), live.len());
+        assert!((report.shares.iter().sum::<f64>() - 1.0).abs() < 1e-12, "{:?}", ops);
+
+        let largest = report.shares.iter().cloned().fold(0.0, f64::max);
+        let smallest = report.shares.iter().cloned().fold(1.0, f64::min);
+        assert_eq!(report.is_unfair(2.0), largest > 2.0 * smallest, "{:?}", ops);
+    }
+
+    let rejoined = fork_fairness(&[ForkOp::Fork(0), ForkOp::Join(0, 1)]);
+    assert_eq!(rejoined.shares, vec![1.0]);
+    assert_eq!(rejoined.max_depth, 0);
+}
+
+#[test]
+fn lost_space_is_what_no_stamp_owns() {
+    let mut rng = Rng(257);
+
+    for _ in 0..500 {
+        let stamps: Vec<Stamp> = (0..rng.below(5))
+            .map(|_| Stamp::new(random_ident(&mut rng, 5).canonical(),
+                                random_event(&mut rng, 3).norm()))
+            .collect();
+        let report = id_space_report(&stamps);
+
+        let owned = (0..256).filter(|&i| stamps.iter().any(|s| owns(&s.id, 8, i))).count();
+        assert_eq!(report.owned, owned as f64 / 256.0, "{:?}", stamps);
+        assert_eq!(report.lost, 1.0 - report.owned);
+        assert_eq!(report.regions, stamps.iter().map(|s| s.id.regions().len()).sum());
+        assert_eq!(report.max_depth, stamps.iter().map(|s| s.id.depth()).max().unwrap_or(0));
+    }
+
+    let (a, b) = Stamp::seed().fork();
+    let (_, b) = b.fork();
+    let report = id_space_report(&[a, b]);
+    assert_eq!((report.owned, report.lost, report.regions), (0.75, 0.25, 2));
+    assert_eq!(id_space_report(&[]).lost, 1.0);
+    assert_eq!(id_space_report(&[Stamp::new(Ident::Zero, Event::seed())]).lost, 1.0);
+}
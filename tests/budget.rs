@@ -0,0 +1,54 @@
+//! Encoding budgets add up to the encoding, and flag stamps that outgrow it.
+
+extern crate itc;
+
+mod common;
+
+use itc::{BudgetAdvice, Ident, Stamp, TYPICAL_STAMP_BYTES};
+
+use common::{Rng, leaf, node, random_event, random_ident};
+
+#[test]
+fn budgets_add_up_to_the_encoding() {
+    let mut rng = Rng(276);
+
+    for _ in 0..2000 {
+        let stamp = Stamp::new(random_ident(&mut rng, 6), random_event(&mut rng, 6));
+        let budget = stamp.encoding_budget();
+        assert_eq!(budget.id_bytes, stamp.id.encode().len());
+        assert_eq!(budget.event_bytes, stamp.event.encode().len());
+        assert_eq!(budget.total_bytes(), stamp.encode().len());
+        assert_eq!(budget.advice.is_empty(), budget.total_bytes() <= TYPICAL_STAMP_BYTES);
+    }
+
+    for &v in &[0, 63, 64, -64, -65, i64::MAX, i64::MIN] {
+        let e = node(v, leaf(v), leaf(0));
+        assert_eq!(e.encoded_len(), e.encode().len(), "encoding {:?}", e);
+    }
+}
+
+#[test]
+fn oversized_stamps_are_flagged() {
+    assert!(Stamp::seed().event().encoding_budget().advice.is_empty());
+
+    // an event seen from many members is large even in normal form
+    let mut kept = Stamp::seed();
+    let mut heard = Stamp::seed().fork().0;
+    for _ in 0..200 {
+        let (a, b) = kept.fork();
+        kept = a;
+        heard = heard.join(&b.event().peek());
+    }
+    let budget = heard.encoding_budget();
+    assert!(budget.total_bytes() > TYPICAL_STAMP_BYTES);
+    assert!(budget.advice.contains(&BudgetAdvice::Prune), "{:?}", budget);
+
+    // an id forked deep into one corner
+    let deep = (0..300).fold(Stamp::seed(), |s, _| s.fork().0);
+    let budget = deep.encoding_budget();
+    assert!(budget.advice.contains(&BudgetAdvice::Rebalance), "{:?}", budget);
+
+    // an event that normalizes to a single leaf
+    let bloated = Stamp::new(Ident::One, (0..9).fold(leaf(1), |e, _| node(0, e.clone(), e)));
+    assert_eq!(bloated.encoding_budget().advice, vec![BudgetAdvice::Compact]);
+}
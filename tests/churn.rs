@@ -0,0 +1,104 @@
+//! A cluster whose membership churns constantly: members arrive by forking the
+//! member holding the largest share of the id space, leave by joining their id
+//! into another member, tick and gossip. Every causal decision is checked
+//! against the exact sets of events each member has seen, and the ids are
+//! checked to stay disjoint, to cover the whole id space and to stay shallow.
+
+extern crate itc;
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use itc::{Ident, Stamp};
+
+use common::Rng;
+
+struct Member {
+    stamp: Stamp,
+    seen: BTreeSet<u64>,
+}
+
+// The depth of the member's largest region, so smaller is a larger share.
+fn share_depth(m: &Member) -> usize {
+    m.stamp.id.regions().iter().map(|r| r.depth()).min().unwrap_or(usize::MAX)
+}
+
+fn check_ids(members: &[Member]) {
+    let mut union = Ident::Zero;
+
+    for m in members {
+        assert!(m.stamp.id != Ident::Zero, "member without an id");
+        union = union.sum_minimal(&m.stamp.id).expect("member ids overlap");
+    }
+
+    assert_eq!(union, Ident::One, "member ids do not cover the id space");
+}
+
+fn check_causality(members: &[Member], r: &mut Rng) {
+    for _ in 0..20 {
+        let a = &members[r.below(members.len() as u64) as usize];
+        let b = &members[r.below(members.len() as u64) as usize];
+
+        assert_eq!(a.stamp.leq(&b.stamp), a.seen.is_subset(&b.seen));
+        assert_eq!(a.stamp.concurrent(&b.stamp),
+                   !a.seen.is_subset(&b.seen) && !b.seen.is_subset(&a.seen));
+    }
+}
+
+#[test]
+fn churny_cluster() {
+    let mut r = Rng(0x2545f4914f6cdd1d);
+    let mut members = vec![Member { stamp: Stamp::seed(), seen: BTreeSet::new() }];
+    let mut next_event = 0;
+    let mut most = 1;
+
+    for step in 0..5000 {
+        let k = r.below(members.len() as u64) as usize;
+
+        match r.below(10) {
+            // arrival
+            0 | 1 if members.len() < 64 => {
+                let largest = (0..members.len()).min_by_key(|&i| share_depth(&members[i])).unwrap();
+                let (a, b) = members[largest].stamp.fork();
+                let seen = members[largest].seen.clone();
+                members[largest].stamp = a;
+                members.push(Member { stamp: b, seen });
+            },
+
+            // departure
+            2 | 3 if members.len() > 1 => {
+                let leaving = members.swap_remove(k);
+                let j = r.below(members.len() as u64) as usize;
+                members[j].stamp = members[j].stamp.join(&leaving.stamp);
+                members[j].seen.extend(leaving.seen);
+            },
+
+            // gossip
+            4..=6 => {
+                let j = r.below(members.len() as u64) as usize;
+                let msg = members[k].stamp.peek();
+                let seen = members[k].seen.clone();
+                members[j].stamp = members[j].stamp.join(&msg);
+                members[j].seen.extend(seen);
+            },
+
+            _ => {
+                members[k].stamp = members[k].stamp.event();
+                members[k].seen.insert(next_event);
+                next_event += 1;
+            },
+        }
+
+        most = std::cmp::max(most, members.len());
+
+        check_ids(&members);
+        if step % 10 == 0 {
+            check_causality(&members, &mut r);
+        }
+
+        let depth = members.iter().map(|m| m.stamp.id.depth()).max().unwrap();
+        let bound = 2 * (64 - (most as u64).leading_zeros() as usize) + 2;
+        assert!(depth <= bound, "id depth {} with at most {} members", depth, most);
+    }
+}
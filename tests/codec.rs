@@ -0,0 +1,56 @@
+//! Stamps written through a `FramedWrite` come back out of a `FramedRead`
+//! unchanged and in order.
+
+#![cfg(feature = "tokio")]
+
+extern crate futures;
+extern crate itc;
+extern crate tokio_util;
+
+mod common;
+
+use futures::executor::block_on;
+use futures::{SinkExt, StreamExt};
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+use itc::Stamp;
+use itc::codec::StampCodec;
+
+use common::{Rng, random_event, random_ident};
+
+#[test]
+fn stamps_round_trip_through_frames() {
+    let mut rng = Rng(213);
+    let stamps: Vec<Stamp> = (0..200)
+        .map(|_| Stamp::new(random_ident(&mut rng, 5), random_event(&mut rng, 6).norm()))
+        .collect();
+
+    let mut written = FramedWrite::new(Vec::new(), StampCodec::new());
+    for stamp in &stamps {
+        block_on(written.send(stamp)).unwrap();
+    }
+    let bytes = written.into_inner();
+
+    let read: Vec<Stamp> = block_on(FramedRead::new(&bytes[..], StampCodec::new())
+        .map(|frame| frame.unwrap())
+        .collect());
+
+    assert_eq!(read.len(), stamps.len());
+    for (a, b) in read.iter().zip(&stamps) {
+        assert_eq!(a.encode(), b.encode());
+    }
+}
+
+#[test]
+fn oversized_frames_are_rejected() {
+    let (a, b) = Stamp::seed().fork();
+    let big = a.event().join(&b.event().fork().0.event());
+
+    let mut written = FramedWrite::new(Vec::new(), StampCodec::new());
+    block_on(written.send(big.clone())).unwrap();
+    let bytes = written.into_inner();
+
+    let max = big.encode().len() - 1;
+    let mut read = FramedRead::new(&bytes[..], StampCodec::with_max_frame_length(max));
+    assert!(block_on(read.next()).unwrap().is_err());
+}
@@ -0,0 +1,190 @@
+//! Helpers shared by the integration tests and the benchmarks.
+
+#![allow(dead_code)]
+
+use std::rc::Rc;
+
+use itc::{Event, Ident, Stamp};
+
+/// Xorshift, so that generated cases reproduce.
+pub struct Rng(pub u64);
+
+impl Rng {
+    pub fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+/// Deterministic bytes to drive `Arbitrary`, so that failures reproduce.
+pub fn inputs(seed: u64) -> Vec<Vec<u8>> {
+    let mut rng = Rng(seed);
+    (0..2000).map(|_| (0..64).map(|_| rng.next() as u8).collect()).collect()
+}
+
+pub fn leaf(n: i64) -> Event {
+    Event::Leaf(n)
+}
+
+pub fn node(n: i64, l: Event, r: Event) -> Event {
+    Event::Node(n, Rc::new(l), Rc::new(r))
+}
+
+pub fn id(l: Ident, r: Ident) -> Ident {
+    Ident::Tuple(Rc::new(l), Rc::new(r))
+}
+
+/// A random event of at most `depth` levels with small values, not
+/// necessarily in normal form.
+pub fn random_event(rng: &mut Rng, depth: u32) -> Event {
+    let n = rng.below(3) as i64;
+
+    if depth == 0 || rng.below(3) == 0 {
+        leaf(n)
+    } else {
+        node(n, random_event(rng, depth - 1), random_event(rng, depth - 1))
+    }
+}
+
+/// A random id of at most `depth` levels, not necessarily normalized.
+pub fn random_ident(rng: &mut Rng, depth: u32) -> Ident {
+    if depth == 0 || rng.below(3) == 0 {
+        if rng.below(2) == 0 { Ident::Zero } else { Ident::One }
+    } else {
+        id(random_ident(rng, depth - 1), random_ident(rng, depth - 1))
+    }
+}
+
+/// Whether `id` owns slot `i` when the id space is cut into `2^depth` equal
+/// slots.
+pub fn owns(id: &Ident, depth: u32, i: u64) -> bool {
+    match *id {
+        Ident::Zero => false,
+        Ident::One => true,
+        Ident::Tuple(ref l, ref r) => {
+            let half = 1 << (depth - 1);
+            if i < half { owns(l, depth - 1, i) } else { owns(r, depth - 1, i - half) }
+        },
+    }
+}
+
+/// The value `e` maps the left end of slot `i` to when the id space is cut
+/// into `2^depth` equal slots.
+pub fn value_at(e: &Event, depth: u32, i: u64) -> i64 {
+    match *e {
+        Event::Leaf(n) => n,
+        Event::Node(n, ref l, _) if depth == 0 => n + value_at(l, 0, 0),
+        Event::Node(n, ref l, ref r) => {
+            let half = 1 << (depth - 1);
+            if i < half {
+                n + value_at(l, depth - 1, i)
+            } else {
+                n + value_at(r, depth - 1, i - half)
+            }
+        },
+    }
+}
+
+/// The id owning only slot `i` of the id space cut into `2^depth` slots.
+pub fn slot_ident(depth: u32, i: u64) -> Ident {
+    if depth == 0 {
+        return Ident::One;
+    }
+
+    let half = 1 << (depth - 1);
+    if i < half {
+        id(slot_ident(depth - 1, i), Ident::Zero)
+    } else {
+        id(Ident::Zero, slot_ident(depth - 1, i - half))
+    }
+}
+
+/// A complete tree `depth` levels deep with random leaves, in normal form.
+pub fn balanced_event(rng: &mut Rng, depth: u32) -> Event {
+    if depth == 0 {
+        leaf(rng.below(8) as i64)
+    } else {
+        node(0, balanced_event(rng, depth - 1), balanced_event(rng, depth - 1)).norm()
+    }
+}
+
+/// One step of a random run over a list of members, naming them by their
+/// place in the list.
+#[derive(Clone, Copy, Debug)]
+pub enum Step {
+    /// Member `k` forks, keeping one half and appending the other.
+    Fork(usize),
+    /// Member `k` ticks.
+    Tick(usize),
+    /// Member `k` hears what member `j` has seen.
+    Merge(usize, usize),
+    /// Member `k` is swap-removed and joins into member `j` of what is left.
+    Leave(usize, usize),
+}
+
+/// `steps` random steps, starting from a single member.
+pub fn random_steps(rng: &mut Rng, steps: usize) -> Vec<Step> {
+    let mut live = 1;
+
+    (0..steps).map(|_| {
+        let k = rng.below(live) as usize;
+
+        match rng.below(5) {
+            0 => {
+                live += 1;
+                Step::Fork(k)
+            },
+            1 | 2 => Step::Tick(k),
+            3 => Step::Merge(k, rng.below(live) as usize),
+            _ if live > 1 => {
+                live -= 1;
+                Step::Leave(k, rng.below(live) as usize)
+            },
+            _ => Step::Tick(k),
+        }
+    }).collect()
+}
+
+/// Applies `step` to `live`, returning the place of the member it changed.
+pub fn apply_step(live: &mut Vec<Stamp>, step: Step) -> usize {
+    match step {
+        Step::Fork(k) => {
+            let (a, b) = live[k].fork();
+            live[k] = a;
+            live.push(b);
+            k
+        },
+        Step::Tick(k) => {
+            live[k] = live[k].event();
+            k
+        },
+        Step::Merge(k, j) => {
+            live[k] = live[k].join(&live[j].peek());
+            k
+        },
+        Step::Leave(k, j) => {
+            let departing = live.swap_remove(k);
+            live[j] = live[j].join(&departing);
+            j
+        },
+    }
+}
+
+/// Every stamp a random run of `steps` forks, ticks, merges and departures
+/// passes through, so that many pairs are causally related.
+pub fn random_history(rng: &mut Rng, steps: usize) -> Vec<Stamp> {
+    let mut live = vec![Stamp::seed()];
+
+    random_steps(rng, steps).into_iter()
+        .map(|step| {
+            let k = apply_step(&mut live, step);
+            live[k].clone()
+        })
+        .collect()
+}
@@ -0,0 +1,82 @@
+//! Cached comparisons agree with comparing the stamps directly.
+
+extern crate itc;
+
+mod common;
+
+use itc::{DigestCache, Ident, Stamp, StampComparator};
+
+use common::{Rng, leaf, node, random_event, random_ident};
+
+// Stamps from ticking and joining a few clocks, so that many pairs are
+// ordered and some are equal.
+fn working_set(seed: u64) -> Vec<Stamp> {
+    let mut rng = Rng(seed);
+    let mut stamps: Vec<Stamp> = Stamp::seed().fork_shards(4).into_iter()
+        .map(|s| Stamp::new(s.id, random_event(&mut rng, 4).norm()))
+        .collect();
+
+    for _ in 0..60 {
+        let k = rng.below(stamps.len() as u64) as usize;
+        let next = match rng.below(3) {
+            0 => stamps[k].event(),
+            1 => stamps[k].join(&stamps[rng.below(stamps.len() as u64) as usize].peek()),
+            _ => stamps[k].clone(),
+        };
+        stamps.push(next);
+    }
+
+    stamps
+}
+
+#[test]
+fn comparator_agrees_with_direct_comparison() {
+    for seed in 1..20 {
+        let stamps = working_set(seed * 208);
+        let mut comparator = StampComparator::new(&stamps);
+
+        for i in 0..stamps.len() {
+            for j in 0..stamps.len() {
+                let (a, b) = (&stamps[i], &stamps[j]);
+                assert_eq!(comparator.leq(i, j), a.event.leq(&b.event), "{:?} <= {:?}", a, b);
+                assert_eq!(comparator.equal(i, j), a.event == b.event);
+                assert_eq!(comparator.concurrent(i, j), a.concurrent(b));
+            }
+        }
+    }
+}
+
+#[test]
+fn digest_cache_is_never_wrong() {
+    for seed in 1..20 {
+        let stamps = working_set(seed * 271);
+        let mut cache = DigestCache::new();
+
+        for stamp in &stamps {
+            cache.insert(stamp);
+        }
+
+        for probe in &stamps {
+            let eq: Vec<bool> = stamps.iter().map(|s| s.event == probe.event).collect();
+            let leq: Vec<bool> = stamps.iter().map(|s| probe.event.leq(&s.event)).collect();
+            assert_eq!(cache.eq_each(probe), eq);
+            assert_eq!(cache.leq_each(probe), leq);
+        }
+    }
+}
+
+#[test]
+fn bloom_keys_depend_only_on_the_event() {
+    let mut rng = Rng(211);
+
+    for _ in 0..2000 {
+        let a = Stamp::new(random_ident(&mut rng, 4).canonical(), random_event(&mut rng, 6));
+        let b = Stamp::new(random_ident(&mut rng, 4).canonical(), random_event(&mut rng, 6));
+        assert_eq!(a.bloom_key() == b.bloom_key(), a.event == b.event, "{:?} and {:?}", a, b);
+    }
+
+    let a = Stamp::new(Ident::One, node(0, leaf(2), leaf(2)));
+    let b = Stamp::new(Ident::Zero, leaf(2));
+    assert_eq!(a.bloom_key(), b.bloom_key());
+    assert_ne!(a.bloom_key(), Stamp::seed().bloom_key());
+}
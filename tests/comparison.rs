@@ -0,0 +1,106 @@
+//! The comparison APIs on stamps never contradict each other: `leq`,
+//! `concurrent`, `causal_cmp`, `PartialEq`/`PartialOrd`, `identical` and
+//! `total_cmp`, and `happens_before`, `happens_after` and `concurrent_with`
+//! on their events, are checked against each other on generated stamps.
+
+extern crate itc;
+
+mod common;
+
+use std::cmp::Ordering;
+
+use itc::{CausalTimestamp, Event, Ident, Stamp};
+
+use common::{Rng, random_event, random_history};
+
+// Stamps from a random run, so that many pairs are causally related, plus
+// stamps with random trees, so that many are not.
+fn stamps(rng: &mut Rng) -> Vec<Stamp> {
+    let mut seen = random_history(rng, 120);
+
+    for _ in 0..40 {
+        seen.push(Stamp::new(Ident::Zero, random_event(rng, 4)));
+    }
+
+    seen
+}
+
+#[test]
+fn comparisons_agree() {
+    let mut rng = Rng(0x9e3779b97f4a7c15);
+    let stamps = stamps(&mut rng);
+
+    for a in &stamps {
+        for b in &stamps {
+            let causal = a.causal_cmp(b);
+
+            assert_eq!(causal, a.partial_cmp(b));
+            assert_eq!(causal, b.causal_cmp(a).map(Ordering::reverse));
+            assert_eq!(causal.is_none(), a.concurrent(b));
+            assert_eq!(a.leq(b), causal.is_some_and(|o| o != Ordering::Greater));
+            assert_eq!(a == b, causal == Some(Ordering::Equal));
+
+            assert_eq!(a.event.happens_before(&b.event), causal == Some(Ordering::Less));
+            assert_eq!(a.event.happens_after(&b.event), causal == Some(Ordering::Greater));
+            assert_eq!(a.event.concurrent_with(&b.event), causal.is_none());
+
+            let total = a.total_cmp(b);
+            assert_eq!(total, b.total_cmp(a).reverse());
+            assert_eq!(total == Ordering::Equal, a == b);
+
+            if let Some(o) = causal {
+                assert_eq!(total, o);
+            }
+
+            if a.identical(b) {
+                assert!(a == b);
+            }
+        }
+
+        assert!(a.identical(a));
+        assert_eq!(a.total_cmp(a), Ordering::Equal);
+    }
+}
+
+#[test]
+fn total_order_is_transitive() {
+    let mut rng = Rng(0x2545f4914f6cdd1d);
+    let mut stamps = stamps(&mut rng);
+
+    stamps.sort_by(|a, b| a.total_cmp(b));
+
+    for i in 0..stamps.len() {
+        for j in i..stamps.len() {
+            assert_ne!(stamps[i].total_cmp(&stamps[j]), Ordering::Greater);
+        }
+    }
+}
+
+#[test]
+fn causal_timestamps_sort_like_total_cmp() {
+    let mut rng = Rng(262);
+    let mut stamps = stamps(&mut rng);
+    for _ in 0..50 {
+        stamps.push(Stamp::new(Ident::One, Event::Leaf(rng.below(5) as i64 - 2)));
+    }
+    let timestamps: Vec<CausalTimestamp> = stamps.iter().map(|s| s.as_causal_timestamp()).collect();
+
+    for (a, ta) in stamps.iter().zip(&timestamps) {
+        for (b, tb) in stamps.iter().zip(&timestamps) {
+            assert_eq!(ta.cmp(tb), a.total_cmp(b));
+            assert_eq!(ta.as_bytes().cmp(tb.as_bytes()), a.total_cmp(b));
+        }
+        assert_eq!(CausalTimestamp::from_bytes(ta.as_bytes().to_vec()), *ta);
+    }
+
+    let negative = Stamp::new(Ident::One, Event::Leaf(-5));
+    let huge = Stamp::new(Ident::One, Event::Leaf(i64::MAX / 2));
+    assert!(negative.as_causal_timestamp() < huge.as_causal_timestamp());
+
+    // the token stays the same size as the trees grow
+    assert!(timestamps.iter().all(|t| t.as_bytes().len() == 32));
+    let wide = Stamp::seed().fork_shards(256).iter().step_by(2).map(|s| s.event())
+        .fold(Stamp::seed().peek(), |all, s| all.join(&s.peek()));
+    assert!(wide.event.size() > 256);
+    assert_eq!(wide.as_causal_timestamp().as_bytes().len(), 32);
+}
@@ -0,0 +1,52 @@
+//! Normalizing an id reaches the same fixed point whatever order `norm`,
+//! `canonical` and `split` are applied in.
+
+#![cfg(feature = "arbitrary")]
+
+extern crate arbitrary;
+extern crate itc;
+
+mod common;
+
+use arbitrary::{Arbitrary, Unstructured};
+use itc::Ident;
+
+fn ids() -> Vec<Ident> {
+    common::inputs(0x2545f4914f6cdd1d).iter()
+        .filter_map(|bytes| Ident::arbitrary(&mut Unstructured::new(bytes)).ok())
+        .collect()
+}
+
+#[test]
+fn canonical_is_a_fixed_point() {
+    for id in ids() {
+        let c = id.canonical();
+        assert_eq!(c.canonical(), c);
+        assert_eq!(c.clone().norm(), c);
+    }
+}
+
+#[test]
+fn norm_then_canonical_agrees_with_canonical() {
+    for id in ids() {
+        assert_eq!(id.clone().norm().canonical(), id.canonical());
+    }
+}
+
+#[test]
+fn split_commutes_with_canonical() {
+    for id in ids() {
+        let (l1, r1) = id.split();
+        let (l2, r2) = id.canonical().split();
+        assert_eq!(l1.canonical(), l2.canonical(), "splitting {:?}", id);
+        assert_eq!(r1.canonical(), r2.canonical(), "splitting {:?}", id);
+    }
+}
+
+#[test]
+fn sum_of_split_is_the_original() {
+    for id in ids() {
+        let (l, r) = id.split();
+        assert_eq!(l.sum(&r).canonical(), id.canonical(), "splitting {:?}", id);
+    }
+}
@@ -0,0 +1,69 @@
+//! Replicated counters never lose or double count an increment.
+
+extern crate itc;
+
+mod common;
+
+use itc::{GCounter, Ident};
+
+use common::{Rng, Step, random_steps};
+
+#[test]
+fn merging_every_replica_sums_every_increment() {
+    for seed in 1..200 {
+        let mut rng = Rng(seed * 264);
+        let mut replicas = vec![GCounter::seed()];
+        let mut total = 0;
+
+        for step in random_steps(&mut rng, 200) {
+            match step {
+                Step::Fork(k) => {
+                    let (a, b) = replicas[k].fork();
+                    replicas[k] = a;
+                    replicas.push(b);
+                },
+                Step::Tick(k) => {
+                    let by = rng.below(4);
+                    replicas[k].increment(by);
+                    total += by;
+                },
+                Step::Merge(k, j) => {
+                    let other = replicas[j].clone();
+                    replicas[k].merge(&other);
+                },
+                Step::Leave(k, j) => {
+                    let departing = replicas.swap_remove(k);
+                    replicas[j] = replicas[j].join(&departing);
+                },
+            }
+
+            for r in &replicas {
+                assert!(r.value() <= total, "seed {}", seed);
+            }
+        }
+
+        let mut merged = replicas[0].clone();
+        for r in &replicas[1..] {
+            merged.merge(r);
+        }
+        assert_eq!(merged.value(), total, "seed {}", seed);
+
+        let joined = replicas[1..].iter().fold(replicas[0].clone(), |all, r| all.join(r));
+        assert_eq!(joined.value(), total, "seed {}", seed);
+        assert_eq!(joined.stamp().id, Ident::One);
+    }
+}
+
+#[test]
+fn concurrent_increments_both_count_once() {
+    let (mut a, mut b) = GCounter::seed().fork();
+    a.increment(3);
+    b.increment(4);
+    assert!(a.stamp().concurrent(b.stamp()));
+
+    let mut merged = a.clone();
+    merged.merge(&b);
+    assert_eq!(merged.value(), 7);
+    merged.merge(&b);
+    assert_eq!(merged.value(), 7);
+}
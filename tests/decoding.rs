@@ -0,0 +1,118 @@
+//! Decoding from a slice and from a reader agree, byte for byte, and bad
+//! input is rejected with the error naming what is wrong with it.
+
+extern crate itc;
+
+mod common;
+
+use std::io::{self, Read};
+
+use itc::{DecodeError, Event, Ident, Stamp};
+
+use common::{Rng, leaf, node, random_event, random_ident};
+
+// Hands out one byte per read, so that decoding cannot lean on slices.
+struct Trickle<'a>(&'a [u8]);
+
+impl<'a> Read for Trickle<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.0.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+
+        buf[0] = self.0[0];
+        self.0 = &self.0[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn slice_and_reader_decoding_agree() {
+    let mut rng = Rng(233);
+
+    for _ in 0..2000 {
+        let stamp = Stamp::new(random_ident(&mut rng, 5), random_event(&mut rng, 10));
+        let bytes = stamp.encode();
+
+        let sliced = Stamp::decode(&bytes).unwrap();
+        let read = Stamp::decode_from(&mut Trickle(&bytes)).unwrap();
+        assert_eq!(sliced.encode(), read.encode());
+        assert_eq!(sliced.event.encode(), stamp.event.norm().encode());
+
+        let event = stamp.event.encode();
+        let sliced = Event::decode(&event).unwrap();
+        let read = Event::decode_from(&mut Trickle(&event)).unwrap();
+        assert_eq!(sliced.encode(), read.encode());
+
+        let id = stamp.id.encode();
+        let read = Ident::decode_from(&mut Trickle(&id)).unwrap();
+        assert_eq!(Ident::decode(&id).unwrap(), read);
+    }
+}
+
+#[test]
+fn slice_and_reader_decoding_fail_alike() {
+    let mut rng = Rng(2233);
+
+    for _ in 0..500 {
+        let bytes = random_event(&mut rng, 6).encode();
+
+        for k in 0..bytes.len() {
+            let sliced = Event::decode(&bytes[..k]).map(|e| e.encode());
+            let read = Event::decode_from(&mut Trickle(&bytes[..k])).map(|e| e.encode());
+            assert!(sliced.is_err());
+            assert_eq!(format!("{:?}", sliced), format!("{:?}", read));
+        }
+    }
+}
+
+#[test]
+fn only_canonical_encodings_are_minimal() {
+    let mut rng = Rng(214);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6);
+        let bytes = e.encode();
+        assert!(Event::is_minimal_encoding(&e.norm().encode()), "encoding {:?}", e);
+        assert_eq!(Event::is_minimal_encoding(&bytes), bytes == e.norm().encode());
+
+        let mut longer = e.norm().encode();
+        longer.push(0);
+        assert!(!Event::is_minimal_encoding(&longer));
+    }
+
+    assert!(Event::is_minimal_encoding(&[0x00]));
+    assert!(!Event::is_minimal_encoding(&[0x80, 0x00]));
+    assert!(!Event::is_minimal_encoding(&node(0, leaf(1), leaf(1)).encode()));
+}
+
+#[test]
+fn short_and_long_input_is_named() {
+    let bytes = node(1, leaf(0), node(0, leaf(2), leaf(0))).encode();
+
+    for k in 0..bytes.len() {
+        match Event::decode(&bytes[..k]) {
+            Err(DecodeError::Truncated) => {},
+            other => panic!("{} of {} bytes decoded as {:?}", k, bytes.len(), other),
+        }
+    }
+
+    let mut longer = bytes.clone();
+    longer.push(7);
+    match Event::decode(&longer) {
+        Err(DecodeError::TrailingBytes) => {},
+        other => panic!("trailing byte decoded as {:?}", other),
+    }
+
+    let mut stamp = Stamp::seed().fork().0.encode();
+    stamp.push(0);
+    match Stamp::decode(&stamp) {
+        Err(DecodeError::TrailingBytes) => {},
+        other => panic!("trailing byte decoded as {:?}", other),
+    }
+
+    match Ident::decode(&[2, 1]) {
+        Err(DecodeError::Truncated) => {},
+        other => panic!("half an id decoded as {:?}", other),
+    }
+}
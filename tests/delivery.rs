@@ -0,0 +1,122 @@
+//! Sorting a shuffled mailbox with `Stamp::partition_deliverable` delivers
+//! every message exactly once, in an order that respects happens-before.
+
+extern crate itc;
+
+mod common;
+
+use itc::Stamp;
+
+use common::Rng;
+
+fn indices(msgs: &[&(Stamp, usize)]) -> Vec<usize> {
+    msgs.iter().map(|msg| msg.1).collect()
+}
+
+#[test]
+fn reorders_a_mailbox_into_delivery_order() {
+    for seed in 1..100 {
+        let mut rng = Rng(seed * 31337);
+        let (receiver, rest) = Stamp::seed().fork();
+        let mut senders = vec![rest];
+
+        for _ in 0..3 {
+            let k = rng.below(senders.len() as u64) as usize;
+            let (a, b) = senders[k].fork();
+            senders[k] = a;
+            senders.push(b);
+        }
+
+        let mut sent: Vec<(Stamp, usize)> = Vec::new();
+
+        for _ in 0..60 {
+            let k = rng.below(senders.len() as u64) as usize;
+
+            if !sent.is_empty() && rng.below(2) == 0 {
+                let heard = sent[rng.below(sent.len() as u64) as usize].0.event.clone();
+                senders[k].event = senders[k].event.join(&heard);
+            }
+
+            senders[k] = senders[k].event();
+            sent.push((senders[k].clone(), sent.len()));
+        }
+
+        let mut mailbox = sent.clone();
+
+        for i in (1..mailbox.len()).rev() {
+            mailbox.swap(i, rng.below(i as u64 + 1) as usize);
+        }
+
+        let mut receiver = receiver;
+        let mut order = Vec::new();
+
+        while !mailbox.is_empty() {
+            let sorted = receiver.partition_deliverable(&mailbox);
+            assert!(!sorted.deliverable.is_empty(), "seed {} stuck", seed);
+            assert!(sorted.seen.is_empty() && sorted.anonymous.is_empty());
+
+            for msg in &sorted.deliverable {
+                receiver.event = receiver.event.join(&msg.0.event);
+                order.push(msg.1);
+            }
+
+            mailbox = sorted.waiting.into_iter().cloned().collect();
+        }
+
+        let mut delivered = order.clone();
+        delivered.sort();
+        assert_eq!(delivered, (0..sent.len()).collect::<Vec<_>>());
+
+        for (k, &i) in order.iter().enumerate() {
+            for &j in &order[k + 1..] {
+                let (earlier, later) = (&sent[i].0.event, &sent[j].0.event);
+                assert!(!later.leq(earlier) || earlier.leq(later), "seed {}", seed);
+            }
+        }
+    }
+}
+
+#[test]
+fn waits_for_a_missing_predecessor() {
+    let (a, b) = Stamp::seed().fork();
+    let a1 = a.event();
+    let a2 = a1.event();
+
+    let msgs = vec![(a2.clone(), 2), (a1.clone(), 1)];
+    let sorted = b.partition_deliverable(&msgs);
+    assert_eq!(indices(&sorted.deliverable), vec![1]);
+    assert_eq!(indices(&sorted.waiting), vec![2]);
+
+    let msgs = vec![(a2, 2)];
+    let sorted = b.partition_deliverable(&msgs);
+    assert!(sorted.deliverable.is_empty());
+    assert_eq!(indices(&sorted.waiting), vec![2]);
+}
+
+#[test]
+fn separates_seen_messages() {
+    let (a, b) = Stamp::seed().fork();
+    let a1 = a.event();
+    let a2 = a1.event();
+    let b = b.join(&a1.peek());
+
+    let msgs = vec![(a1, 1), (a2, 2)];
+    let sorted = b.partition_deliverable(&msgs);
+    assert_eq!(indices(&sorted.seen), vec![1]);
+    assert_eq!(indices(&sorted.deliverable), vec![2]);
+    assert!(sorted.waiting.is_empty());
+}
+
+#[test]
+fn rejects_anonymous_messages() {
+    let (a, b) = Stamp::seed().fork();
+    let a1 = a.event();
+    let a2 = a1.event();
+
+    let msgs = vec![(a1.peek(), 1), (a2, 2), (a.peek(), 0)];
+    let sorted = b.partition_deliverable(&msgs);
+    assert_eq!(indices(&sorted.anonymous), vec![1]);
+    assert_eq!(indices(&sorted.seen), vec![0]);
+    assert_eq!(indices(&sorted.waiting), vec![2]);
+    assert!(sorted.deliverable.is_empty());
+}
@@ -0,0 +1,87 @@
+//! Deltas between events: joining a delta onto its base gives back the
+//! later event, and the delta is the smallest that does.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, Ident, ItcError};
+
+use common::{Rng, leaf, node, random_event, random_ident, value_at};
+
+fn cases(seed: u64) -> Vec<(Event, Ident)> {
+    let mut rng = Rng(seed);
+
+    (0..2000).filter_map(|_| {
+        let e = random_event(&mut rng, 6).norm();
+        let id = random_ident(&mut rng, 5).canonical();
+        if id == Ident::Zero { None } else { Some((e, id)) }
+    }).collect()
+}
+
+#[test]
+fn advance_delta_joins_back_to_the_tick() {
+    for (e, id) in cases(223) {
+        let (next, delta) = e.advance_delta(&id);
+        assert_eq!(next.encode(), e.event(&id).encode(), "ticking {:?} with {:?}", e, id);
+        assert_eq!(e.join(&delta).encode(), next.encode(), "ticking {:?} with {:?}", e, id);
+    }
+}
+
+#[test]
+fn advance_delta_is_minimal() {
+    for (e, id) in cases(2223) {
+        let (next, delta) = e.advance_delta(&id);
+        assert_eq!(delta.encode(), next.subtract(&e).unwrap().encode(),
+                   "ticking {:?} with {:?}", e, id);
+    }
+}
+
+#[test]
+fn subtract_keeps_only_what_is_ahead() {
+    let mut rng = Rng(207);
+
+    for _ in 0..2000 {
+        let base = random_event(&mut rng, 5).norm();
+        let later = base.join(&random_event(&mut rng, 5).norm());
+        let delta = later.subtract(&base).unwrap();
+        assert_eq!(base.join(&delta).encode(), later.encode(), "{:?} from {:?}", later, base);
+
+        for i in 0..256 {
+            let (l, b) = (value_at(&later, 8, i), value_at(&base, 8, i));
+            assert_eq!(value_at(&delta, 8, i), if l > b { l } else { 0 });
+        }
+    }
+}
+
+#[test]
+fn subtract_keeps_negative_base_values() {
+    let base = node(0, leaf(-3), node(-1, leaf(0), leaf(2)));
+    let later = node(0, leaf(-3), node(-1, leaf(4), leaf(2)));
+    let delta = later.subtract(&base).unwrap();
+    assert_eq!(base.join(&delta).encode(), later.norm().encode());
+
+    // behind or level with base, the delta is zero or base's negative value
+    for i in 0..256 {
+        let (l, b) = (value_at(&later, 8, i), value_at(&base, 8, i));
+        assert_eq!(value_at(&delta, 8, i), if l > b { l } else { b.min(0) }, "slot {}", i);
+    }
+    assert_eq!(value_at(&delta, 8, 0), -3);
+    assert_eq!(value_at(&delta, 8, 255), 0);
+}
+
+#[test]
+fn subtract_needs_a_dominated_base() {
+    let mut rng = Rng(2207);
+
+    for _ in 0..2000 {
+        let a = random_event(&mut rng, 5);
+        let b = random_event(&mut rng, 5);
+
+        match b.subtract(&a) {
+            Ok(_) => assert!(a.leq(&b)),
+            Err(ItcError::NotDominating) => assert!(!a.leq(&b)),
+            Err(e) => panic!("subtracting {:?} from {:?}: {}", a, b, e),
+        }
+    }
+}
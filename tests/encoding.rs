@@ -0,0 +1,59 @@
+//! The wire encoding is stable for events in normal form, and decoding only
+//! ever produces events in normal form.
+
+#![cfg(feature = "arbitrary")]
+
+extern crate arbitrary;
+extern crate itc;
+
+mod common;
+
+use arbitrary::{Arbitrary, Unstructured};
+use itc::{EncodingVersion, Event};
+
+fn events() -> Vec<Event> {
+    common::inputs(0x9e3779b97f4a7c15).iter()
+        .filter_map(|bytes| Event::arbitrary(&mut Unstructured::new(bytes)).ok())
+        .collect()
+}
+
+fn is_normal(e: &Event) -> bool {
+    Event::is_minimal_encoding(&e.encode())
+}
+
+#[test]
+fn normal_form_round_trips() {
+    for e in events() {
+        let bytes = e.norm().encode();
+        assert_eq!(Event::decode(&bytes).unwrap().encode(), bytes, "encoding {:?}", e);
+    }
+}
+
+#[test]
+fn decoding_normalizes() {
+    for e in events() {
+        let decoded = Event::decode(&e.encode()).unwrap();
+        assert!(is_normal(&decoded), "decoding {:?}", e);
+        assert_eq!(decoded.encode(), e.norm().encode(), "decoding {:?}", e);
+    }
+}
+
+#[test]
+fn versioned_decoding_normalizes() {
+    for e in events() {
+        for &version in &[EncodingVersion::V1, EncodingVersion::V2] {
+            let (decoded, _) = Event::decode_versioned(&e.encode_versioned(version)).unwrap();
+            assert!(is_normal(&decoded), "decoding {:?} as {:?}", e, version);
+            assert_eq!(decoded.encode(), e.norm().encode(), "decoding {:?} as {:?}", e, version);
+        }
+    }
+}
+
+#[test]
+fn decoding_arbitrary_bytes_normalizes() {
+    for bytes in common::inputs(0x9e3779b97f4a7c15) {
+        if let Ok(decoded) = Event::decode_from(&mut &bytes[..]) {
+            assert!(is_normal(&decoded), "decoding {:?}", bytes);
+        }
+    }
+}
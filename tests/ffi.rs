@@ -0,0 +1,63 @@
+//! Walking ids and events through the C handle API rebuilds them exactly.
+
+#![cfg(feature = "ffi")]
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, Ident, Stamp};
+use itc::ffi::*;
+
+use common::{Rng, id, node, random_event, random_ident};
+
+unsafe fn rebuild_event(e: EventRef) -> Event {
+    let n = itc_event_value(e);
+
+    if itc_event_is_leaf(e) {
+        assert!(itc_event_left(e).is_null() && itc_event_right(e).is_null());
+        Event::Leaf(n)
+    } else {
+        node(n, rebuild_event(itc_event_left(e)), rebuild_event(itc_event_right(e)))
+    }
+}
+
+unsafe fn rebuild_ident(i: IdentRef) -> Ident {
+    match itc_ident_kind(i) {
+        ITC_IDENT_ZERO => Ident::Zero,
+        ITC_IDENT_ONE => Ident::One,
+        kind => {
+            assert_eq!(kind, ITC_IDENT_TUPLE);
+            id(rebuild_ident(itc_ident_left(i)), rebuild_ident(itc_ident_right(i)))
+        },
+    }
+}
+
+#[test]
+fn walks_stamps_from_their_roots() {
+    let mut rng = Rng(235);
+
+    for _ in 0..500 {
+        let stamp = Stamp::new(random_ident(&mut rng, 5), random_event(&mut rng, 6));
+        let (i, e) = unsafe {
+            (rebuild_ident(itc_stamp_id(&stamp)), rebuild_event(itc_stamp_event(&stamp)))
+        };
+
+        assert_eq!(i, stamp.id);
+        assert_eq!(e.encode(), stamp.event.encode());
+        assert_eq!(e.size(), stamp.event.size());
+    }
+}
+
+#[test]
+fn leaves_have_no_children() {
+    let id = Ident::One;
+    let e = Event::Leaf(3);
+
+    unsafe {
+        assert!(itc_ident_left(IdentRef::new(&id)).is_null());
+        assert!(itc_ident_right(IdentRef::new(&id)).is_null());
+        assert!(itc_event_left(EventRef::new(&e)).is_null());
+        assert_eq!(itc_event_value(EventRef::new(&e)), 3);
+    }
+}
@@ -0,0 +1,68 @@
+//! Flat events hold the same trees, and compare like them when their shapes
+//! match.
+
+extern crate itc;
+
+mod common;
+
+use std::cmp::Ordering;
+
+use itc::{Event, FlatEvent};
+
+use common::{Rng, leaf, node, random_event};
+
+// An event of the same shape as `e`, with new values.
+fn revalue(rng: &mut Rng, e: &Event) -> Event {
+    let n = rng.below(3) as i64;
+
+    match *e {
+        Event::Leaf(_) => leaf(n),
+        Event::Node(_, ref l, ref r) => node(n, revalue(rng, l), revalue(rng, r)),
+    }
+}
+
+#[test]
+fn flat_events_round_trip() {
+    let mut rng = Rng(253);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 7);
+        let flat = e.to_flat();
+        assert_eq!(Event::from_flat(&flat).encode(), e.encode(), "flattening {:?}", e);
+        assert_eq!(FlatEvent::new(flat.shape().to_vec(), flat.values().to_vec()).unwrap(), flat);
+        assert_eq!((flat.min(), flat.max()), (e.min(), e.max()), "flattening {:?}", e);
+    }
+}
+
+#[test]
+fn flat_events_of_one_shape_compare_like_trees() {
+    let mut rng = Rng(2253);
+
+    for _ in 0..2000 {
+        let a = random_event(&mut rng, 6);
+        let b = revalue(&mut rng, &a);
+        let (fa, fb) = (a.to_flat(), b.to_flat());
+        assert!(fa.same_shape(&fb));
+        assert_eq!(fa.leq(&fb), Some(a.leq(&b)), "comparing {:?} with {:?}", a, b);
+        let order = match (a.leq(&b), b.leq(&a)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        };
+        assert_eq!(fa.causal_cmp(&fb), order, "comparing {:?} with {:?}", a, b);
+    }
+
+    let a = node(0, leaf(1), node(1, leaf(0), leaf(2)));
+    let b = node(1, leaf(0), node(0, leaf(1), leaf(3)));
+    assert_eq!(a.to_flat().causal_cmp(&b.to_flat()), Some(Ordering::Less));
+    assert_eq!(a.to_flat().leq(&leaf(0).to_flat()), None);
+}
+
+#[test]
+fn inconsistent_shapes_are_rejected() {
+    assert!(FlatEvent::new(vec![0b11], vec![0; 5]).is_ok());
+    assert!(FlatEvent::new(vec![0b10], vec![0; 3]).is_err());
+    assert!(FlatEvent::new(vec![0b1], vec![0; 5]).is_err());
+    assert!(FlatEvent::new(vec![0b1, 1], vec![0; 3]).is_err());
+}
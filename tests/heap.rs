@@ -0,0 +1,57 @@
+//! The stamp heap pops in causal order, whatever order stamps were pushed in.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Stamp, StampHeap};
+
+use common::{Rng, random_history};
+
+fn drain(stamps: &[Stamp], order: &[usize]) -> Vec<usize> {
+    let mut heap = StampHeap::new();
+    for &i in order {
+        heap.push(stamps[i].clone(), i);
+    }
+    std::iter::from_fn(|| heap.pop().map(|(_, i)| i)).collect()
+}
+
+#[test]
+fn pops_never_precede_what_happened_before() {
+    let mut rng = Rng(274);
+    let stamps = random_history(&mut rng, 300);
+
+    let pushed: Vec<usize> = (0..stamps.len()).collect();
+    let mut shuffled = pushed.clone();
+    for i in (1..shuffled.len()).rev() {
+        shuffled.swap(i, rng.below(i as u64 + 1) as usize);
+    }
+
+    let popped = drain(&stamps, &pushed);
+    for (k, &i) in popped.iter().enumerate() {
+        for &j in &popped[k + 1..] {
+            assert!(!stamps[j].event.happens_before(&stamps[i].event),
+                    "{} popped before {}", i, j);
+        }
+    }
+
+    // the push order only matters among equal events
+    let events = |order: &[usize]| -> Vec<Vec<u8>> {
+        order.iter().map(|&i| stamps[i].event.encode()).collect()
+    };
+    assert_eq!(events(&popped), events(&drain(&stamps, &shuffled)));
+}
+
+#[test]
+fn equal_stamps_pop_in_push_order() {
+    let mut heap = StampHeap::new();
+    for i in 0..5 {
+        heap.push(Stamp::seed(), i);
+    }
+
+    assert_eq!(heap.len(), 5);
+    assert_eq!(heap.peek().map(|(_, &i)| i), Some(0));
+    let popped: Vec<i32> = std::iter::from_fn(|| heap.pop().map(|(_, i)| i)).collect();
+    assert_eq!(popped, vec![0, 1, 2, 3, 4]);
+    assert!(heap.is_empty());
+}
@@ -0,0 +1,165 @@
+//! Operations on ids, checked slot by slot against the regions they own.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Ident, ItcError};
+
+use common::{Rng, owns, random_ident};
+
+#[test]
+fn least_upper_id_owns_every_input_region() {
+    let mut rng = Rng(275);
+
+    for _ in 0..2000 {
+        let k = rng.below(5) as usize;
+        let ids: Vec<Ident> = (0..k).map(|_| random_ident(&mut rng, 5)).collect();
+        let upper = Ident::least_upper_id(&ids);
+
+        for i in 0..256 {
+            assert_eq!(owns(&upper, 8, i), ids.iter().any(|id| owns(id, 8, i)), "{:?}", ids);
+        }
+        assert_eq!(upper, upper.canonical());
+    }
+
+    let (a, b) = Ident::One.split();
+    assert_eq!(Ident::least_upper_id(&[]), Ident::Zero);
+    assert_eq!(Ident::least_upper_id(&[a.clone(), a, b]), Ident::One);
+}
+
+#[test]
+fn owns_leaf_rounds_the_slot_count_up() {
+    let mut rng = Rng(205);
+
+    for _ in 0..500 {
+        let id = random_ident(&mut rng, 5);
+        let total = 1 + rng.below(200) as usize;
+        let depth = total.next_power_of_two().trailing_zeros();
+
+        // Each slot at `depth` is this many slots at depth 8.
+        let width = 1 << (8 - depth);
+        for i in 0..256 {
+            let whole = (0..width).all(|j| owns(&id, 8, i as u64 * width + j));
+            assert_eq!(id.owns_leaf(total, i), i < total && whole,
+                       "slot {} of {} in {:?}", i, total, id);
+        }
+    }
+
+    let (a, b) = Ident::One.split();
+    let (c, _) = b.split();
+    assert_eq!((0..6).filter(|&i| c.owns_leaf(6, i)).collect::<Vec<_>>(), vec![4, 5]);
+    assert_eq!((0..8).filter(|&i| a.owns_leaf(8, i)).collect::<Vec<_>>(), vec![0, 1, 2, 3]);
+    assert!(Ident::One.owns_leaf(3, 2) && !Ident::One.owns_leaf(3, 3));
+}
+
+#[test]
+fn split_reporting_reports_the_deeper_half() {
+    let mut rng = Rng(210);
+
+    for _ in 0..2000 {
+        let id = random_ident(&mut rng, 5).canonical();
+        let ((l, r), depth) = id.split_reporting();
+        assert_eq!((l.clone(), r.clone()), id.split());
+        assert_eq!(depth as usize, l.depth().max(r.depth()), "splitting {:?}", id);
+    }
+
+    let ((_, b), depth) = Ident::One.split_reporting();
+    assert_eq!(depth, 1);
+    assert_eq!(b.split_reporting().1, 2);
+}
+
+#[test]
+fn reserve_split_divides_the_fork_capacity() {
+    let mut rng = Rng(227);
+
+    for _ in 0..1000 {
+        let id = random_ident(&mut rng, 4).canonical();
+        assert_eq!(id.fork_capacity(8), (0..256).filter(|&i| owns(&id, 8, i)).count() as u64);
+
+        if id == Ident::Zero {
+            continue;
+        }
+
+        let reserve = rng.below(5) as u32;
+        let (kept, given) = id.reserve_split(reserve);
+        assert_eq!(kept.sum_minimal(&given).unwrap(), id, "reserving {} of {:?}", reserve, id);
+        assert_eq!(kept.fork_capacity(16) + given.fork_capacity(16), id.fork_capacity(16));
+    }
+
+    for reserve in 0..6 {
+        let (kept, given) = Ident::One.reserve_split(reserve);
+        assert_eq!(given.fork_capacity(8), 128 >> reserve);
+        assert_eq!(kept.fork_capacity(8), 256 - (128 >> reserve));
+    }
+    assert_eq!(Ident::One.reserve_split(0), Ident::One.split());
+    assert_eq!(Ident::One.fork_capacity(70), u64::MAX);
+}
+
+#[test]
+fn partitions_give_each_node_its_slots() {
+    let mut rng = Rng(239);
+
+    for _ in 0..500 {
+        let total = 1 + rng.below(100) as usize;
+        let mut plan = Vec::new();
+        let mut start = 0;
+        while start < total {
+            let end = start + 1 + rng.below(total as u64 / 3 + 1) as usize;
+            plan.push((rng.below(4), start..end));
+            start = end;
+        }
+
+        let ids = Ident::from_partitions(&plan, total);
+        let padded = total.next_power_of_two();
+        for i in 0..padded {
+            let owners: Vec<u64> = ids.iter()
+                .filter(|&(_, id)| id.owns_leaf(padded, i))
+                .map(|(&node, _)| node)
+                .collect();
+            let planned: Vec<u64> = plan.iter()
+                .filter(|&(_, slots)| slots.contains(&i) && i < total)
+                .map(|&(node, _)| node)
+                .collect();
+            assert_eq!(owners, planned, "slot {} of {:?}", i, plan);
+        }
+    }
+
+    let whole = Ident::from_partitions(&[("all", 0..8)], 8);
+    assert_eq!(whole["all"], Ident::One);
+    let trimmed = Ident::from_partitions(&[("a", 0..2), ("b", 2..9)], 3);
+    assert!(trimmed["b"].owns_leaf(4, 2));
+    assert!(!trimmed["b"].owns_leaf(4, 3));
+}
+
+#[test]
+fn sum_minimal_rejects_exactly_the_overlapping_ids() {
+    let mut rng = Rng(259);
+    let (mut summed, mut rejected) = (0, 0);
+
+    for _ in 0..5000 {
+        let a = random_ident(&mut rng, 5);
+        let b = random_ident(&mut rng, 5);
+        let overlap = (0..256).any(|i| owns(&a, 8, i) && owns(&b, 8, i));
+
+        match a.sum_minimal(&b) {
+            Ok(sum) => {
+                assert!(!overlap, "summed {:?} and {:?}", a, b);
+                assert_eq!(sum, sum.canonical());
+                for i in 0..256 {
+                    assert_eq!(owns(&sum, 8, i), owns(&a, 8, i) || owns(&b, 8, i));
+                }
+                summed += 1;
+            },
+            Err(ItcError::Overlap) => {
+                assert!(overlap, "refused {:?} and {:?}", a, b);
+                rejected += 1;
+            },
+            Err(e) => panic!("summing {:?} and {:?}: {}", a, b, e),
+        }
+    }
+    assert!(summed > 100 && rejected > 100, "{} summed, {} rejected", summed, rejected);
+
+    let (a, b) = Ident::One.split();
+    assert_eq!(a.sum_minimal(&b).unwrap(), Ident::One);
+}
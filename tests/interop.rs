@@ -0,0 +1,148 @@
+//! Conversions to and from a foreign implementation with its own types and
+//! its own codec for the canonical encoding.
+
+#![cfg(feature = "interop")]
+
+extern crate itc;
+
+mod common;
+
+use itc::interop::{ForeignEvent, ForeignIdent};
+use itc::{Event, Ident};
+
+use common::{Rng, leaf, node, random_event, random_ident};
+
+#[derive(Debug, PartialEq)]
+enum Tree {
+    Leaf(i64),
+    Node(i64, Box<Tree>, Box<Tree>),
+}
+
+fn read_varint(bytes: &[u8], at: &mut usize) -> Option<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*at)?;
+        *at += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn parse(bytes: &[u8], at: &mut usize) -> Option<Tree> {
+    let header = read_varint(bytes, at)?;
+    let zigzag = header >> 1;
+    let n = (zigzag >> 1) as i64 ^ -((zigzag & 1) as i64);
+
+    if header & 1 == 1 {
+        Some(Tree::Node(n, Box::new(parse(bytes, at)?), Box::new(parse(bytes, at)?)))
+    } else {
+        Some(Tree::Leaf(n))
+    }
+}
+
+fn write(tree: &Tree, out: &mut Vec<u8>) {
+    let zigzag = |n: i64| ((n << 1) ^ (n >> 63)) as u64;
+
+    match *tree {
+        Tree::Leaf(n) => write_varint(out, zigzag(n) << 1),
+        Tree::Node(n, ref l, ref r) => {
+            write_varint(out, zigzag(n) << 1 | 1);
+            write(l, out);
+            write(r, out);
+        },
+    }
+}
+
+impl ForeignEvent for Tree {
+    type Error = ();
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        write(self, &mut out);
+        out
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Tree, ()> {
+        let mut at = 0;
+        match parse(bytes, &mut at) {
+            Some(tree) if at == bytes.len() => Ok(tree),
+            _ => Err(()),
+        }
+    }
+}
+
+// Foreign values that are nothing but their encoding.
+#[derive(Debug)]
+struct Opaque(Vec<u8>);
+
+impl ForeignEvent for Opaque {
+    type Error = ();
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Opaque, ()> {
+        Ok(Opaque(bytes.to_vec()))
+    }
+}
+
+impl ForeignIdent for Opaque {
+    type Error = ();
+
+    fn to_canonical_bytes(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn from_canonical_bytes(bytes: &[u8]) -> Result<Opaque, ()> {
+        Ok(Opaque(bytes.to_vec()))
+    }
+}
+
+// The foreign tree an event should convert to.
+fn tree(e: &Event) -> Tree {
+    match *e {
+        Event::Leaf(n) => Tree::Leaf(n),
+        Event::Node(n, ref l, ref r) => Tree::Node(n, Box::new(tree(l)), Box::new(tree(r))),
+    }
+}
+
+#[test]
+fn foreign_types_round_trip() {
+    let mut rng = Rng(261);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6).norm();
+        let foreign: Tree = e.to_foreign().unwrap();
+        assert_eq!(foreign, tree(&e));
+        assert_eq!(Event::from_foreign(&foreign).unwrap().encode(), e.encode());
+
+        let id = random_ident(&mut rng, 5);
+        let foreign: Opaque = id.to_foreign().unwrap();
+        assert_eq!(Ident::from_foreign::<Opaque>(&foreign).unwrap(), id);
+    }
+}
+
+#[test]
+fn foreign_events_are_normalized_on_the_way_in() {
+    let unnormalized = Tree::Node(0, Box::new(Tree::Leaf(2)), Box::new(Tree::Leaf(2)));
+    assert_eq!(Event::from_foreign(&unnormalized).unwrap().encode(), leaf(2).encode());
+
+    let deep = node(1, leaf(0), node(0, leaf(3), leaf(0)));
+    assert_eq!(Event::from_foreign(&tree(&deep)).unwrap().encode(), deep.encode());
+    assert!(Event::from_foreign(&Opaque(vec![0x81])).is_err());
+    assert!(Ident::from_foreign(&Opaque(vec![2, 1])).is_err());
+}
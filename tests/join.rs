@@ -0,0 +1,169 @@
+//! Joining events: the result dominates both sides pointwise and nothing
+//! more, and joining normal forms always gives the same canonical tree.
+
+extern crate itc;
+
+mod common;
+
+use itc::{DecodeError, Event, ItcError};
+
+use common::{Rng, balanced_event, leaf, node, random_event, slot_ident, value_at};
+
+#[test]
+fn joining_into_itself_is_byte_stable() {
+    let mut rng = Rng(236);
+
+    for _ in 0..500 {
+        let e = random_event(&mut rng, 6).norm();
+        let bytes = e.encode();
+        let mut clock = e.clone();
+
+        for _ in 0..10 {
+            clock = clock.join(&clock);
+            assert_eq!(clock.encode(), bytes, "joining {:?}", e);
+            clock = clock.join(&e);
+            assert_eq!(clock.encode(), bytes, "joining {:?}", e);
+        }
+    }
+}
+
+#[test]
+fn join_is_independent_of_order() {
+    let mut rng = Rng(2236);
+
+    for _ in 0..500 {
+        let a = random_event(&mut rng, 6).norm();
+        let b = random_event(&mut rng, 6).norm();
+        assert_eq!(a.join(&b).encode(), b.join(&a).encode(), "joining {:?} and {:?}", a, b);
+        assert!(Event::is_minimal_encoding(&a.join(&b).encode()), "joining {:?} and {:?}", a, b);
+    }
+}
+
+#[test]
+fn join_shares_what_is_unchanged() {
+    let mut rng = Rng(219);
+
+    for &depth in &[4, 8, 10] {
+        let base = balanced_event(&mut rng, depth);
+        let a = base.event(&slot_ident(depth, 0));
+        let b = base.event(&slot_ident(depth, (1 << depth) - 1));
+
+        let dominated = base.join(&a);
+        assert_eq!(dominated.encode(), a.encode());
+        assert!(Event::unique_node_count(&[&base, &a, &dominated]) <=
+                Event::unique_node_count(&[&base, &a]) + 1);
+
+        // Only the paths down to the two ticked slots need new nodes.
+        let joined = a.join(&b);
+        let new = Event::unique_node_count(&[&base, &a, &b, &joined]) -
+            Event::unique_node_count(&[&base, &a, &b]);
+        assert!(new <= 2 * depth as usize, "{} new nodes at depth {}", new, depth);
+        assert!(a.leq(&joined) && b.leq(&joined));
+    }
+}
+
+// The join as the paper defines it, normalizing at every level.
+fn paper_join(a: &Event, b: &Event) -> Event {
+    match (a, b) {
+        (&Event::Leaf(n1), &Event::Leaf(n2)) => Event::Leaf(std::cmp::max(n1, n2)),
+        (&Event::Leaf(n1), _) => paper_join(&node(n1, leaf(0), leaf(0)), b),
+        (_, &Event::Leaf(n2)) => paper_join(a, &node(n2, leaf(0), leaf(0))),
+        (&Event::Node(n1, _, _), &Event::Node(n2, _, _)) if n1 > n2 => paper_join(b, a),
+        (&Event::Node(n1, ref l1, ref r1), &Event::Node(n2, ref l2, ref r2)) => {
+            let l = paper_join(l1, &(**l2).clone().lift(n2 - n1));
+            let r = paper_join(r1, &(**r2).clone().lift(n2 - n1));
+            Event::node(n1, l, r)
+        },
+    }
+}
+
+#[test]
+fn join_matches_the_paper_definition() {
+    let mut rng = Rng(269);
+
+    for _ in 0..2000 {
+        let a = random_event(&mut rng, 7).norm();
+        let b = random_event(&mut rng, 7).norm();
+        assert_eq!(a.join(&b).encode(), paper_join(&a, &b).encode(),
+                   "joining {:?} and {:?}", a, b);
+    }
+
+    for _ in 0..200 {
+        let a = balanced_event(&mut rng, 6);
+        let b = a.event(&slot_ident(6, rng.below(64)));
+        assert_eq!(a.join(&b).encode(), paper_join(&a, &b).encode());
+    }
+}
+
+// Each node the join builds is normalized once, as it is built, and shared
+// subtrees are not normalized at all, so the nodes built count the
+// normalizations.
+#[test]
+fn join_normalizes_only_the_nodes_it_builds() {
+    let mut rng = Rng(2269);
+
+    for &depth in &[8, 12, 14] {
+        let base = balanced_event(&mut rng, depth);
+        let a = base.event(&slot_ident(depth, rng.below(1 << depth)));
+        let b = base.event(&slot_ident(depth, rng.below(1 << depth)));
+
+        let joined = a.join(&b);
+        let built = Event::unique_node_count(&[&a, &b, &joined]) -
+            Event::unique_node_count(&[&a, &b]);
+        assert!(built <= 2 * depth as usize, "{} nodes built at depth {}", built, depth);
+        assert_eq!(joined.encode(), paper_join(&a, &b).encode());
+    }
+}
+
+#[test]
+fn join_encoded_matches_merge_all() {
+    let mut rng = Rng(204);
+
+    for count in 0..40 {
+        let events: Vec<Event> = (0..count).map(|_| random_event(&mut rng, 5).norm()).collect();
+        let blobs: Vec<Vec<u8>> = events.iter().map(|e| e.encode()).collect();
+        let slices: Vec<&[u8]> = blobs.iter().map(|b| &b[..]).collect();
+
+        let merged = Event::merge_all(&events);
+        let folded = events.iter().fold(Event::seed(), |acc, e| acc.join(e));
+        assert_eq!(merged.encode(), folded.encode());
+        assert_eq!(Event::join_encoded(&slices).unwrap().encode(), merged.encode());
+    }
+}
+
+#[test]
+fn join_encoded_reports_a_bad_blob() {
+    let good = leaf(1).encode();
+    let blobs: Vec<&[u8]> = vec![&good, &[], &good];
+
+    match Event::join_encoded(&blobs) {
+        Err(ItcError::Decode(DecodeError::Truncated)) => {},
+        other => panic!("joined to {:?}", other),
+    }
+}
+
+#[test]
+fn join_capped_flattens_below_the_cap() {
+    let mut rng = Rng(228);
+
+    for _ in 0..500 {
+        let a = random_event(&mut rng, 6).norm();
+        let b = random_event(&mut rng, 6).norm();
+        let joined = a.join(&b);
+
+        for cap in 0..7 {
+            let capped = a.join_capped(&b, cap);
+            assert!(capped.depth() <= cap, "capping {:?} at {}", joined, cap);
+
+            // each region at the cap takes the highest value the join has in it
+            let width = 1 << (8 - cap);
+            for i in 0..256 {
+                let start = i / width * width;
+                let highest = (start..start + width).map(|j| value_at(&joined, 8, j)).max();
+                assert_eq!(Some(value_at(&capped, 8, i)), highest,
+                           "capping {:?} at {}", joined, cap);
+            }
+        }
+        assert_eq!(a.join_capped(&b, 64).encode(), joined.encode());
+    }
+}
@@ -0,0 +1,78 @@
+//! Leased ids expire unless renewed, and always come back to the pool.
+
+extern crate itc;
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use itc::{Ident, LeasedIdPool};
+
+use common::Rng;
+
+// The free ids and the leased ones cover the pool's id space exactly once.
+fn check_partition(pool: &LeasedIdPool, leased: &[(Ident, u64)]) {
+    let all = leased.iter()
+        .try_fold(pool.free().clone(), |all, (id, _)| all.sum_minimal(id))
+        .expect("leased ids overlap");
+    assert_eq!(all, Ident::One);
+    assert_eq!(pool.leased(), leased.len());
+}
+
+#[test]
+fn expired_leases_are_swept_back() {
+    let mut rng = Rng(254);
+    let start = Instant::now();
+    let at = |secs: u64| start + Duration::from_secs(secs);
+
+    for _ in 0..50 {
+        let mut pool = LeasedIdPool::new(Ident::One);
+        let mut leased: Vec<(Ident, u64)> = Vec::new();
+
+        for now in 0..200 {
+            match rng.below(4) {
+                0 => {
+                    let ttl = 1 + rng.below(20);
+                    if let Some(id) = pool.acquire_lease_at(at(now), Duration::from_secs(ttl)) {
+                        leased.push((id, now + ttl));
+                    }
+                },
+                1 if !leased.is_empty() => {
+                    let k = rng.below(leased.len() as u64) as usize;
+                    let ttl = 1 + rng.below(20);
+                    assert!(pool.renew_at(at(now), &leased[k].0, Duration::from_secs(ttl)));
+                    leased[k].1 = now + ttl;
+                },
+                2 if !leased.is_empty() => {
+                    let k = rng.below(leased.len() as u64) as usize;
+                    let (id, _) = leased.swap_remove(k);
+                    assert!(pool.release(&id));
+                    assert!(!pool.release(&id));
+                },
+                _ => {},
+            }
+
+            let mut swept = pool.sweep_expired_at(at(now));
+            let mut expired: Vec<Ident> = leased.iter()
+                .filter(|&&(_, until)| until <= now)
+                .map(|(id, _)| id.clone())
+                .collect();
+            leased.retain(|&(_, until)| until > now);
+            swept.sort_by_key(|id| id.encode());
+            expired.sort_by_key(|id| id.encode());
+            assert_eq!(swept, expired);
+
+            for id in &expired {
+                assert!(!pool.renew_at(at(now), id, Duration::from_secs(1)));
+            }
+            check_partition(&pool, &leased);
+        }
+    }
+}
+
+#[test]
+fn an_empty_pool_has_nothing_to_lease() {
+    let mut pool = LeasedIdPool::new(Ident::Zero);
+    assert!(pool.acquire_lease(Duration::from_secs(1)).is_none());
+    assert!(pool.sweep_expired().is_empty());
+}
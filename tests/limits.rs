@@ -0,0 +1,59 @@
+//! Checked operations fail with `LimitExceeded` exactly when their result
+//! would break the limits.
+
+extern crate itc;
+
+use itc::{Event, Ident, ItcError, Limits, Stamp};
+
+fn exceeded<T>(result: Result<T, ItcError>) -> bool {
+    matches!(result, Err(ItcError::LimitExceeded))
+}
+
+#[test]
+fn fork_within_and_beyond_the_depth() {
+    let limits = Limits::new(2, 100);
+    let (a, _) = Stamp::seed().try_fork(&limits).unwrap();
+    let (a, _) = a.try_fork(&limits).unwrap();
+    assert_eq!(a.id.depth(), 2);
+    assert!(exceeded(a.try_fork(&limits)));
+}
+
+#[test]
+fn event_within_and_beyond_the_size() {
+    let (a, _) = Stamp::seed().fork();
+    let (a, _) = a.fork();
+
+    let next = a.try_event(&Limits::new(10, 5)).unwrap();
+    assert_eq!(next.event.size(), 5);
+    assert!(exceeded(a.try_event(&Limits::new(10, 4))));
+    assert!(exceeded(a.try_event(&Limits::new(1, 100))));
+}
+
+#[test]
+fn join_within_and_beyond_the_size() {
+    let (a, b) = Stamp::seed().fork();
+    let (a, b) = (a.event(), b.event());
+
+    let joined = a.try_join(&b, &Limits::new(0, 1)).unwrap();
+    assert_eq!(joined.event, Event::Leaf(1));
+
+    let (b, c) = b.fork();
+    let (b, c) = (b.event(), c);
+    assert!(exceeded(b.try_join(&c, &Limits::new(1, 100))));
+    assert!(b.try_join(&c, &Limits::new(2, 100)).is_ok());
+}
+
+#[test]
+fn construction_within_and_beyond_the_limits() {
+    let limits = Limits::new(1, 3);
+    let half = Ident::try_tuple(Ident::One, Ident::Zero, &limits).unwrap();
+    assert!(exceeded(Ident::try_tuple(half.clone(), Ident::Zero, &limits)));
+
+    let e = Event::try_node(0, Event::Leaf(1), Event::Leaf(0), &limits).unwrap();
+    assert!(exceeded(Event::try_node(0, e.clone(), Event::Leaf(0), &limits)));
+    assert!(Event::try_node(0, Event::Leaf(2), Event::Leaf(2), &Limits::new(0, 1)).is_ok());
+
+    assert!(Stamp::try_new(half.clone(), e.clone(), &limits).is_ok());
+    assert!(exceeded(Stamp::try_new(half, e, &Limits::new(0, 3))));
+    assert!(Stamp::try_new(Ident::One, Event::Leaf(9), &Limits::default()).is_ok());
+}
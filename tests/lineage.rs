@@ -0,0 +1,97 @@
+//! Lineage proofs verify for ids really forked off the seed, and fail once
+//! anything in them is changed.
+
+#![cfg(feature = "lineage")]
+
+extern crate itc;
+
+mod common;
+
+use itc::{Ident, Side};
+use itc::lineage::{LineageIdent, LineageProof, seed_digest, verify_lineage};
+
+use common::Rng;
+
+const SECRET: &[u8] = b"cluster secret";
+
+fn descendant(rng: &mut Rng, forks: usize) -> LineageIdent {
+    let mut id = LineageIdent::seed(SECRET);
+
+    for _ in 0..forks {
+        let (l, r) = id.split();
+        id = if rng.below(2) == 0 { l } else { r };
+    }
+
+    id
+}
+
+fn flipped(side: Side) -> Side {
+    match side {
+        Side::Left => Side::Right,
+        Side::Right => Side::Left,
+    }
+}
+
+#[test]
+fn valid_proofs_verify() {
+    let mut rng = Rng(201);
+    let seed = seed_digest(SECRET);
+
+    for forks in 0..12 {
+        let id = descendant(&mut rng, forks);
+        let proof = id.prove_lineage();
+        assert_eq!(proof.steps.len(), forks);
+        assert_eq!(&proof.id, id.ident());
+        assert!(verify_lineage(&proof, &seed));
+    }
+}
+
+#[test]
+fn tampered_proofs_fail() {
+    let mut rng = Rng(2201);
+    let seed = seed_digest(SECRET);
+
+    for forks in 1..12 {
+        let proof = descendant(&mut rng, forks).prove_lineage();
+        let k = rng.below(forks as u64) as usize;
+
+        let mut side = proof.clone();
+        side.steps[k].side = flipped(side.steps[k].side);
+        assert!(!verify_lineage(&side, &seed));
+
+        let mut digest = proof.clone();
+        digest.steps[k].digest[0] ^= 1;
+        assert!(!verify_lineage(&digest, &seed));
+
+        let mut id = proof.clone();
+        id.id = Ident::One;
+        assert!(!verify_lineage(&id, &seed));
+
+        let mut short = proof.clone();
+        short.steps.pop();
+        assert!(!verify_lineage(&short, &seed));
+
+        assert!(!verify_lineage(&proof, &seed_digest(b"another secret")));
+    }
+}
+
+#[test]
+fn proofs_cannot_be_extended_without_the_secret() {
+    let seed = seed_digest(SECRET);
+    let (left, _) = LineageIdent::seed(SECRET).split();
+    let proof = left.prove_lineage();
+
+    // Forking the same id under another secret reuses every public part of
+    // the proof but cannot produce a link the real seed accepts.
+    let (forged, _) = LineageIdent::seed(b"guess").split();
+    let (forged, _) = forged.split();
+    let forged = forged.prove_lineage();
+
+    let mut extended = LineageProof { id: forged.id.clone(), steps: proof.steps.clone() };
+    extended.steps.push(forged.steps[1].clone());
+    assert!(!verify_lineage(&extended, &seed));
+
+    let (real, _) = left.split();
+    assert_eq!(real.ident(), &extended.id);
+    assert!(verify_lineage(&real.prove_lineage(), &seed));
+}
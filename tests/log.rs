@@ -0,0 +1,101 @@
+//! Compacted logs replay to the same stamps as the logs they came from.
+
+extern crate itc;
+
+mod common;
+
+use itc::{LogOp, StampLog, compact_log};
+
+use common::Rng;
+
+// A random history, with forks often joined straight back and the same
+// members often merging again.
+fn random_log(rng: &mut Rng) -> StampLog {
+    let mut log = StampLog::new();
+    let mut live = vec![0];
+    let mut next = 1;
+
+    for _ in 0..40 {
+        let k = live[rng.below(live.len() as u64) as usize];
+
+        match rng.below(6) {
+            0 => {
+                log.push(LogOp::Fork(k));
+                live.push(next);
+                next += 1;
+            },
+            1 => {
+                log.push(LogOp::Fork(k));
+                log.push(LogOp::Join(k, next));
+                next += 1;
+            },
+            2 => log.push(LogOp::Event(k)),
+            3 | 4 => {
+                let j = live[rng.below(live.len() as u64) as usize];
+                log.push(LogOp::Merge(k, j));
+            },
+            _ => {
+                let j = live[rng.below(live.len() as u64) as usize];
+                if j != k {
+                    log.push(LogOp::Join(k, j));
+                    live.retain(|&m| m != j);
+                }
+            },
+        }
+    }
+
+    log
+}
+
+#[test]
+fn compacting_keeps_the_replay() {
+    let mut rng = Rng(255);
+
+    for _ in 0..500 {
+        let log = random_log(&mut rng);
+        let compacted = compact_log(&log);
+        assert!(compacted.ops().len() <= log.ops().len());
+
+        let (before, after) = (log.replay(), compacted.replay());
+        assert_eq!(before.len(), after.len(), "compacting {:?}", log);
+        for (a, b) in before.iter().zip(&after) {
+            assert!(a.identical(b), "compacting {:?}", log);
+        }
+
+        // nothing is left to cancel
+        assert_eq!(compact_log(&compacted), compacted);
+    }
+}
+
+#[test]
+fn nested_cancelling_forks_all_go() {
+    let mut log = StampLog::new();
+    for &op in &[LogOp::Fork(0), LogOp::Fork(0), LogOp::Join(0, 2), LogOp::Join(0, 1),
+                 LogOp::Event(0)] {
+        log.push(op);
+    }
+    assert_eq!(compact_log(&log).ops(), &[LogOp::Event(0)]);
+}
+
+#[test]
+fn merges_repeated_later_are_superseded() {
+    let mut log = StampLog::new();
+    for &op in &[LogOp::Fork(0), LogOp::Fork(1), LogOp::Merge(0, 1), LogOp::Event(1),
+                 LogOp::Merge(0, 2), LogOp::Merge(0, 1), LogOp::Event(0)] {
+        log.push(op);
+    }
+    let compacted = compact_log(&log);
+    assert_eq!(compacted.ops(), &[LogOp::Fork(0), LogOp::Fork(1), LogOp::Event(1),
+                                  LogOp::Merge(0, 2), LogOp::Merge(0, 1), LogOp::Event(0)]);
+    for (a, b) in log.replay().iter().zip(&compacted.replay()) {
+        assert!(a.identical(b));
+    }
+
+    // a tick in between may build on what the first merge brought
+    let mut log = StampLog::new();
+    for &op in &[LogOp::Fork(0), LogOp::Event(1), LogOp::Merge(0, 1), LogOp::Event(0),
+                 LogOp::Merge(0, 1)] {
+        log.push(op);
+    }
+    assert_eq!(compact_log(&log), log);
+}
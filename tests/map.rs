@@ -0,0 +1,69 @@
+//! Per-key registers keep the causally latest write, merging concurrent ones.
+
+extern crate itc;
+
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use itc::{Event, Ident, InsertOutcome, Stamp, StampMap};
+
+use common::{Rng, random_event};
+
+#[test]
+fn writes_are_stored_dropped_or_merged() {
+    let merges = Rc::new(Cell::new(0));
+    let counted = merges.clone();
+    let mut map = StampMap::new(move |_: &&str, a: &String, b: &String| {
+        counted.set(counted.get() + 1);
+        format!("{}+{}", a, b)
+    });
+
+    let (a, b) = Stamp::seed().fork();
+    let (a1, b1) = (a.event(), b.event());
+    let a2 = a1.event();
+
+    assert_eq!(map.insert("k", &a2, "a2".to_string()), InsertOutcome::Stored);
+    assert_eq!(map.insert("k", &a1, "a1".to_string()), InsertOutcome::Dropped);
+    assert_eq!(map.get(&"k").unwrap(), "a2");
+
+    assert_eq!(map.insert("k", &b1, "b1".to_string()), InsertOutcome::Merged);
+    assert_eq!(map.get(&"k").unwrap(), "a2+b1");
+    assert_eq!(merges.get(), 1);
+
+    let later = a2.join(&b1).event();
+    assert_eq!(map.insert("k", &later, "c".to_string()), InsertOutcome::Stored);
+    assert_eq!(map.insert("k", &b1, "late".to_string()), InsertOutcome::Dropped);
+    assert_eq!(map.get(&"k").unwrap(), "c");
+    assert_eq!(merges.get(), 1);
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn each_key_holds_the_join_of_its_writes() {
+    let mut rng = Rng(242);
+    let mut map = StampMap::new(|_: &u64, a: &u64, b: &u64| a + b);
+    let mut joined = vec![None::<Event>; 4];
+
+    for _ in 0..2000 {
+        let key = rng.below(4);
+        let stamp = Stamp::new(Ident::One, random_event(&mut rng, 4).norm());
+        let outcome = map.insert(key, &stamp, 1);
+
+        let expected = match joined[key as usize] {
+            None => InsertOutcome::Stored,
+            Some(ref e) if stamp.event.leq(e) => InsertOutcome::Dropped,
+            Some(ref e) if e.leq(&stamp.event) => InsertOutcome::Stored,
+            Some(_) => InsertOutcome::Merged,
+        };
+        assert_eq!(outcome, expected);
+
+        let all = match joined[key as usize].take() {
+            None => stamp.event.norm(),
+            Some(e) => e.join(&stamp.event),
+        };
+        assert_eq!(map.event(&key).unwrap().encode(), all.encode());
+        joined[key as usize] = Some(all);
+    }
+}
@@ -0,0 +1,220 @@
+//! Trees that take the same value everywhere normalize to a single leaf, however
+//! many levels deep the collapse has to reach, and the checked and unchecked
+//! normalizations agree on them.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, ItcError};
+
+use common::{Rng, leaf, node, random_event};
+
+fn check_collapses(e: &Event, v: i64) {
+    assert_eq!(e.norm().encode(), leaf(v).encode(), "normalizing {:?}", e);
+    assert_eq!(e.try_norm().unwrap().encode(), leaf(v).encode(), "normalizing {:?}", e);
+    assert!(*e == leaf(v), "{:?} != {:?}", e, leaf(v));
+}
+
+// A tree taking `v` everywhere, with the value spread over its levels at
+// random.
+fn uniform(r: &mut Rng, v: i64, depth: u32) -> Event {
+    let n = r.below(5) as i64 - 2;
+
+    if depth == 0 || r.below(4) == 0 {
+        return leaf(v);
+    }
+
+    node(n, uniform(r, v - n, depth - 1), uniform(r, v - n, depth - 1))
+}
+
+#[test]
+fn nested_equal_leaves_collapse() {
+    check_collapses(&node(1, leaf(2), node(0, leaf(2), leaf(2))), 3);
+    check_collapses(&node(2, leaf(1), node(1, leaf(0), leaf(0))), 3);
+    check_collapses(&node(0, node(1, leaf(0), leaf(0)), node(0, leaf(1), node(0, leaf(1), leaf(1)))), 1);
+}
+
+#[test]
+fn deep_chains_collapse() {
+    // every level wraps the tree below in a node of the same value
+    let mut e = leaf(7);
+    for depth in 0..100 {
+        e = if depth % 2 == 0 {
+            node(-1, leaf(e.max() + 1), e.lift(1))
+        } else {
+            node(0, e.clone(), leaf(e.max()))
+        };
+    }
+    check_collapses(&e, 7);
+}
+
+#[test]
+fn uniform_trees_collapse() {
+    let mut r = Rng(0x5851f42d4c957f2d);
+
+    for _ in 0..2000 {
+        let v = r.below(9) as i64 - 4;
+        check_collapses(&uniform(&mut r, v, 8), v);
+    }
+}
+
+#[test]
+fn uniform_subtrees_collapse_in_place() {
+    let mut r = Rng(0x14057b7ef767814f);
+
+    for _ in 0..2000 {
+        let v = r.below(5) as i64;
+        let e = node(0, uniform(&mut r, v, 6), leaf(v + 1)).norm();
+        assert_eq!(e.encode(), node(v, leaf(0), leaf(1)).encode(), "normalizing to {:?}", e);
+    }
+}
+
+#[test]
+fn events_are_equal_when_their_normal_forms_are() {
+    let mut rng = Rng(230);
+
+    for _ in 0..2000 {
+        let a = random_event(&mut rng, 6);
+        let b = match rng.below(2) {
+            0 => uniform_like(&mut rng, &a),
+            _ => random_event(&mut rng, 6),
+        };
+        let expected = a.norm().encode() == b.norm().encode();
+        assert_eq!(a == b, expected, "comparing {:?} with {:?}", a, b);
+        assert!(a == a.clone() && a == a.norm());
+    }
+
+    // equal bounds are not enough
+    let (a, b) = (node(0, leaf(0), leaf(2)), node(0, leaf(2), leaf(0)));
+    assert_eq!((a.min(), a.max()), (b.min(), b.max()));
+    assert!(a != b);
+}
+
+// The same values as `e` at every point, built from different nodes.
+fn uniform_like(r: &mut Rng, e: &Event) -> Event {
+    match *e {
+        Event::Leaf(n) => uniform(r, n, 2),
+        Event::Node(n, ref e1, ref e2) => {
+            let d = r.below(3) as i64 - 1;
+            node(n - d, uniform_like(r, e1).lift(d), uniform_like(r, e2).lift(d))
+        },
+    }
+}
+
+#[test]
+fn node_builds_the_normal_form() {
+    let mut rng = Rng(209);
+
+    for _ in 0..2000 {
+        let n = rng.below(4) as i64;
+        let l = random_event(&mut rng, 5).norm();
+        let r = random_event(&mut rng, 5).norm();
+        assert_eq!(Event::node(n, l.clone(), r.clone()).encode(), node(n, l, r).norm().encode());
+    }
+
+    assert_eq!(Event::node(2, leaf(3), leaf(3)).encode(), leaf(5).encode());
+    assert_eq!(Event::node(0, leaf(3), leaf(1)).encode(), node(1, leaf(2), leaf(0)).encode());
+}
+
+// A random event whose values are often near the ends of the range.
+fn extreme_event(rng: &mut Rng, depth: u32) -> Event {
+    let n = match rng.below(6) {
+        0 => i64::MAX - rng.below(3) as i64,
+        1 => i64::MIN + rng.below(3) as i64,
+        _ => rng.below(5) as i64 - 2,
+    };
+
+    if depth == 0 || rng.below(3) == 0 {
+        leaf(n)
+    } else {
+        node(n, extreme_event(rng, depth - 1), extreme_event(rng, depth - 1))
+    }
+}
+
+// Event trees with room for any sum of two values.
+enum Wide {
+    Leaf(i128),
+    Node(i128, Box<Wide>, Box<Wide>),
+}
+
+impl Wide {
+    fn value(&self) -> i128 {
+        match *self {
+            Wide::Leaf(n) | Wide::Node(n, _, _) => n,
+        }
+    }
+
+    fn sink(self, m: i128) -> Wide {
+        match self {
+            Wide::Leaf(n) => Wide::Leaf(n - m),
+            Wide::Node(n, l, r) => Wide::Node(n - m, l, r),
+        }
+    }
+
+    fn fits(&self) -> bool {
+        let fits = |n: i128| n >= i64::MIN as i128 && n <= i64::MAX as i128;
+        match *self {
+            Wide::Leaf(n) => fits(n),
+            Wide::Node(n, ref l, ref r) => fits(n) && l.fits() && r.fits(),
+        }
+    }
+
+    fn narrow(&self) -> Event {
+        match *self {
+            Wide::Leaf(n) => leaf(n as i64),
+            Wide::Node(n, ref l, ref r) => node(n as i64, l.narrow(), r.narrow()),
+        }
+    }
+}
+
+// Normalizes without overflowing, also telling whether the normal form of
+// every subtree fits in an `i64`.
+fn wide_norm(e: &Event) -> (Wide, bool) {
+    match *e {
+        Event::Leaf(n) => (Wide::Leaf(n as i128), true),
+        Event::Node(n, ref l, ref r) => {
+            let ((l, l_fits), (r, r_fits)) = (wide_norm(l), wide_norm(r));
+            let normal = match (l, r) {
+                (Wide::Leaf(a), Wide::Leaf(b)) if a == b => Wide::Leaf(n as i128 + a),
+                (l, r) => {
+                    let m = l.value().min(r.value());
+                    Wide::Node(n as i128 + m, Box::new(l.sink(m)), Box::new(r.sink(m)))
+                },
+            };
+            let fits = l_fits && r_fits && normal.fits();
+            (normal, fits)
+        },
+    }
+}
+
+#[test]
+fn try_norm_reports_overflow() {
+    let mut rng = Rng(249);
+    let mut overflowed = 0;
+
+    for _ in 0..5000 {
+        let e = extreme_event(&mut rng, 5);
+        let (normal, fits) = wide_norm(&e);
+
+        match e.try_norm() {
+            Ok(n) => {
+                assert!(fits, "normalized {:?}", e);
+                assert_eq!(n.encode(), normal.narrow().encode(), "normalizing {:?}", e);
+            },
+            Err(ItcError::Overflow) => {
+                assert!(!fits, "failed to normalize {:?}", e);
+                overflowed += 1;
+            },
+            Err(err) => panic!("normalizing {:?}: {}", e, err),
+        }
+    }
+    assert!(overflowed > 0);
+
+    assert_eq!(node(i64::MAX - 1, leaf(1), leaf(1)).try_norm().unwrap().encode(),
+               leaf(i64::MAX).encode());
+    match node(0, leaf(-1), leaf(i64::MAX)).try_norm() {
+        Err(ItcError::Overflow) => {},
+        other => panic!("normalized to {:?}", other),
+    }
+}
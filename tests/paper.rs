@@ -0,0 +1,125 @@
+//! The example run of Figure 4 in the ITC paper, transcribed step by step,
+//! and a longer fork/event/join run in the same manner, checking the exact
+//! ids and event trees produced at every step. Stamps are written as in the
+//! paper: `(i, e)`, with `(n, l, r)` for an event node.
+
+extern crate itc;
+
+use std::rc::Rc;
+
+use itc::{Event, Ident, Stamp};
+
+fn zero() -> Ident {
+    Ident::Zero
+}
+
+fn one() -> Ident {
+    Ident::One
+}
+
+fn id(l: Ident, r: Ident) -> Ident {
+    Ident::Tuple(Rc::new(l), Rc::new(r))
+}
+
+fn leaf(n: i64) -> Event {
+    Event::Leaf(n)
+}
+
+fn node(n: i64, l: Event, r: Event) -> Event {
+    Event::Node(n, Rc::new(l), Rc::new(r))
+}
+
+fn check(s: &Stamp, i: Ident, e: Event) {
+    assert_eq!(s.id, i);
+    assert_eq!(s.event.encode(), e.encode(), "{:?} != {:?}", s.event, e);
+}
+
+#[test]
+fn figure_4() {
+    // (1, 0) at the root of the figure.
+    let seed = Stamp::seed();
+    check(&seed, one(), leaf(0));
+
+    // fork: ((1, 0), 0) and ((0, 1), 0).
+    let (a, b) = seed.fork();
+    check(&a, id(one(), zero()), leaf(0));
+    check(&b, id(zero(), one()), leaf(0));
+
+    // event on both: ((1, 0), (0, 1, 0)) and ((0, 1), (0, 0, 1)).
+    let a = a.event();
+    let b = b.event();
+    check(&a, id(one(), zero()), node(0, leaf(1), leaf(0)));
+    check(&b, id(zero(), one()), node(0, leaf(0), leaf(1)));
+
+    // fork the first: (((1, 0), 0), (0, 1, 0)) and (((0, 1), 0), (0, 1, 0));
+    // event on the second: ((0, 1), (0, 0, 2)).
+    let (a1, a2) = a.fork();
+    let b = b.event();
+    check(&a1, id(id(one(), zero()), zero()), node(0, leaf(1), leaf(0)));
+    check(&a2, id(id(zero(), one()), zero()), node(0, leaf(1), leaf(0)));
+    check(&b, id(zero(), one()), node(0, leaf(0), leaf(2)));
+
+    // event on the first: (((1, 0), 0), (0, (1, 1, 0), 0)); join the other
+    // two: (((0, 1), 1), (1, 0, 1)).
+    let a1 = a1.event();
+    let c = a2.join(&b);
+    check(&a1, id(id(one(), zero()), zero()),
+          node(0, node(1, leaf(1), leaf(0)), leaf(0)));
+    check(&c, id(id(zero(), one()), one()), node(1, leaf(0), leaf(1)));
+
+    // event on the join, growing the half it owns outright:
+    // (((0, 1), 1), (1, 0, 2)).
+    let c = c.event();
+    check(&c, id(id(zero(), one()), one()), node(1, leaf(0), leaf(2)));
+    assert!(a1.concurrent(&c));
+
+    // join everything back: (1, (1, (0, 1, 0), 2)).
+    let d = a1.join(&c);
+    check(&d, one(), node(1, node(0, leaf(1), leaf(0)), leaf(2)));
+    assert!(a1.leq(&d) && c.leq(&d));
+}
+
+#[test]
+fn longer_run() {
+    let seed = Stamp::seed();
+    check(&seed, one(), leaf(0));
+
+    let (a, b) = seed.fork();
+    check(&a, id(one(), zero()), leaf(0));
+    check(&b, id(zero(), one()), leaf(0));
+
+    let a = a.event();
+    check(&a, id(one(), zero()), node(0, leaf(1), leaf(0)));
+
+    let (b1, b2) = b.fork();
+    check(&b1, id(zero(), id(one(), zero())), leaf(0));
+    check(&b2, id(zero(), id(zero(), one())), leaf(0));
+
+    let b1 = b1.event();
+    check(&b1, id(zero(), id(one(), zero())),
+          node(0, leaf(0), node(0, leaf(1), leaf(0))));
+
+    let b2 = b2.event().event();
+    check(&b2, id(zero(), id(zero(), one())),
+          node(0, leaf(0), node(0, leaf(0), leaf(2))));
+
+    assert!(a.concurrent(&b1));
+    assert!(a.concurrent(&b2));
+    assert!(b1.concurrent(&b2));
+
+    let c = a.join(&b1);
+    check(&c, id(one(), id(one(), zero())),
+          node(0, leaf(1), node(0, leaf(1), leaf(0))));
+    assert!(a.leq(&c) && b1.leq(&c));
+
+    let c = c.event();
+    check(&c, id(one(), id(one(), zero())),
+          node(0, leaf(2), node(0, leaf(1), leaf(0))));
+
+    let d = c.join(&b2);
+    check(&d, one(), node(1, leaf(1), node(0, leaf(0), leaf(1))));
+    assert!(c.leq(&d) && b2.leq(&d));
+
+    let d = d.event();
+    check(&d, one(), leaf(2));
+}
@@ -0,0 +1,125 @@
+//! Regions of the id space, checked against the leaves of the trees naming
+//! them.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, Ident, IdentPath, ItcError, Side};
+
+use common::{Rng, leaf, node, owns, random_event, random_ident, value_at};
+
+#[test]
+fn extreme_regions_are_the_leftmost_extreme_leaves() {
+    let mut rng = Rng(225);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6);
+        let leaves = e.leaf_values();
+
+        let (min, at) = e.min_region();
+        assert_eq!(min, e.min(), "in {:?}", e);
+        assert_eq!(leaves.iter().find(|&&(_, v)| v == min).unwrap().0, at, "in {:?}", e);
+
+        let (max, at) = e.max_region();
+        assert_eq!(max, e.max(), "in {:?}", e);
+        assert_eq!(leaves.iter().find(|&&(_, v)| v == max).unwrap().0, at, "in {:?}", e);
+    }
+
+    let e = node(1, leaf(0), node(2, leaf(3), leaf(0)));
+    assert_eq!(e.min_region(), (1, IdentPath::new(vec![Side::Left])));
+    assert_eq!(e.max_region(), (6, IdentPath::new(vec![Side::Right, Side::Left])));
+    assert_eq!(leaf(4).max_region(), (4, IdentPath::root()));
+}
+
+#[test]
+fn smallest_forkable_halves_the_deepest_region() {
+    let mut rng = Rng(231);
+
+    for _ in 0..2000 {
+        let id = random_ident(&mut rng, 5);
+        let child = match id.smallest_forkable() {
+            Some(child) => child,
+            None => {
+                assert_eq!(id.canonical(), Ident::Zero);
+                continue;
+            },
+        };
+
+        let deepest = id.regions().iter().map(|r| r.depth()).max().unwrap();
+        let regions = child.regions();
+        assert_eq!(regions.len(), 1, "forking {:?}", id);
+        assert_eq!(regions[0].depth(), deepest + 1, "forking {:?}", id);
+        assert_eq!(regions[0].steps().last(), Some(&Side::Right));
+        for i in 0..256 {
+            assert!(!owns(&child, 8, i) || owns(&id, 8, i), "forking {:?}", id);
+        }
+    }
+
+    assert_eq!(Ident::One.smallest_forkable(), Some(Ident::One.split().1));
+    assert_eq!(Ident::Zero.smallest_forkable(), None);
+}
+
+// The slots at depth 8 that `path` covers.
+fn covered(path: &IdentPath) -> std::ops::Range<u64> {
+    let width = 1 << (8 - path.depth());
+    let first = path.steps().iter()
+        .fold(0, |i, side| 2 * i + if *side == Side::Right { 1 } else { 0 });
+    first * width..(first + 1) * width
+}
+
+#[test]
+fn region_depth_counts_the_leaves_in_owned_regions() {
+    let mut rng = Rng(251);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6);
+        let id = random_ident(&mut rng, 4);
+        let deepest = e.leaf_values().iter()
+            .filter(|&(path, _)| covered(path).any(|i| owns(&id, 8, i)))
+            .map(|(path, _)| path.depth())
+            .max()
+            .unwrap_or(0);
+        assert_eq!(e.region_depth(&id), deepest, "{:?} within {:?}", e, id);
+    }
+
+    let e = node(0, leaf(1), node(0, node(0, leaf(1), leaf(0)), leaf(2)));
+    let (a, b) = Ident::One.split();
+    assert_eq!((e.region_depth(&a), e.region_depth(&b)), (1, 3));
+    assert_eq!(e.region_depth(&Ident::Zero), 0);
+}
+
+#[test]
+fn set_region_rewrites_only_the_owned_space() {
+    let mut rng = Rng(272);
+    let (mut raised, mut refused) = (0, 0);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 5).norm();
+        let id = random_ident(&mut rng, 4);
+        let v = rng.below(8) as i64;
+        let lowers = (0..256).any(|i| owns(&id, 8, i) && value_at(&e, 8, i) > v);
+
+        let set = match e.set_region(&id, v, false) {
+            Ok(set) => {
+                assert!(!lowers, "set {:?} to {} in {:?}", id, v, e);
+                assert!(e.leq(&set));
+                raised += 1;
+                set
+            },
+            Err(ItcError::Monotonicity) => {
+                assert!(lowers, "refused to set {:?} to {} in {:?}", id, v, e);
+                refused += 1;
+                e.set_region(&id, v, true).unwrap()
+            },
+            Err(err) => panic!("setting {:?} to {} in {:?}: {}", id, v, e, err),
+        };
+
+        assert!(Event::is_minimal_encoding(&set.encode()));
+        for i in 0..256 {
+            let expected = if owns(&id, 8, i) { v } else { value_at(&e, 8, i) };
+            assert_eq!(value_at(&set, 8, i), expected, "set {:?} to {} in {:?}", id, v, e);
+        }
+    }
+    assert!(raised > 200 && refused > 200, "{} raised, {} refused", raised, refused);
+}
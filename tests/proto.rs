@@ -0,0 +1,59 @@
+//! Protobuf messages round-trip, read events into normal form, and put every
+//! field on the wire with the tag and type `proto/itc.proto` gives it.
+
+#![cfg(feature = "protobuf")]
+
+extern crate itc;
+extern crate prost;
+
+mod common;
+
+use itc::{Event, Ident, Stamp, proto};
+use prost::Message;
+
+use common::{Rng, id, leaf, node, random_event, random_ident};
+
+#[test]
+fn stamps_round_trip() {
+    let mut rng = Rng(229);
+
+    for _ in 0..1000 {
+        let stamp = Stamp::new(random_ident(&mut rng, 5), random_event(&mut rng, 6).norm());
+        let bytes = stamp.to_proto().encode_to_vec();
+        let back = Stamp::from_proto(&proto::Stamp::decode(&bytes[..]).unwrap()).unwrap();
+
+        assert_eq!(back.id, stamp.id);
+        assert_eq!(back.event.encode(), stamp.event.encode());
+    }
+}
+
+#[test]
+fn events_are_read_into_normal_form() {
+    let mut rng = Rng(2229);
+
+    for _ in 0..1000 {
+        let e = random_event(&mut rng, 6);
+        let back = Event::from_proto(&e.to_proto()).unwrap();
+        assert_eq!(back.encode(), e.norm().encode(), "reading {:?}", e);
+    }
+
+    let twins = node(1, leaf(2), leaf(2));
+    assert_eq!(Event::from_proto(&twins.to_proto()).unwrap().encode(), leaf(3).encode());
+}
+
+#[test]
+fn wire_format_follows_the_schema() {
+    // Ident: bool one = 1, Ident left = 2, Ident right = 3.
+    assert_eq!(Ident::One.to_proto().encode_to_vec(), vec![0x08, 0x01]);
+    assert_eq!(id(Ident::One, Ident::Zero).to_proto().encode_to_vec(),
+               vec![0x12, 0x02, 0x08, 0x01, 0x1a, 0x00]);
+
+    // Event: sint64 value = 1, Event left = 2, Event right = 3.
+    assert_eq!(leaf(-1).to_proto().encode_to_vec(), vec![0x08, 0x01]);
+    assert_eq!(node(1, leaf(0), leaf(2)).to_proto().encode_to_vec(),
+               vec![0x08, 0x02, 0x12, 0x00, 0x1a, 0x02, 0x08, 0x04]);
+
+    // Stamp: Ident id = 1, Event event = 2.
+    assert_eq!(Stamp::seed().to_proto().encode_to_vec(),
+               vec![0x0a, 0x02, 0x08, 0x01, 0x12, 0x00]);
+}
@@ -0,0 +1,136 @@
+//! The registry tracks the id each node holds as the cluster churns, and
+//! names the nodes in views of a stamp.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Ident, ItcError, NodeRegistry, NodeStamp, Stamp};
+
+use common::Rng;
+
+// The ids on record cover the id space exactly once between them.
+fn check_partition(registry: &NodeRegistry) {
+    let all = registry.nodes()
+        .try_fold(Ident::Zero, |all, (_, id)| all.sum_minimal(id))
+        .expect("ids on record overlap");
+    assert_eq!(all, Ident::One);
+}
+
+#[test]
+fn ids_stay_on_record_through_churn() {
+    let mut rng = Rng(218);
+    let mut registry = NodeRegistry::new();
+    let mut members = vec![registry.seed(0).unwrap()];
+    let mut next = 1;
+
+    for _ in 0..500 {
+        let k = rng.below(members.len() as u64) as usize;
+
+        if members.len() > 1 && rng.below(3) == 0 {
+            let departing = members.swap_remove(k);
+            let heir = rng.below(members.len() as u64) as usize;
+            let node = departing.node;
+            registry.reclaim(&mut members[heir], departing).unwrap();
+            assert!(!registry.contains(node));
+        } else {
+            let child = registry.fork(&mut members[k], next).unwrap();
+            members.push(child);
+            next += 1;
+        }
+
+        assert_eq!(registry.len(), members.len());
+        for m in &members {
+            assert_eq!(registry.ident(m.node), Some(&m.stamp.id));
+        }
+        check_partition(&registry);
+    }
+}
+
+#[test]
+fn unknown_and_duplicate_nodes_are_refused() {
+    let mut registry = NodeRegistry::new();
+    let mut a = registry.seed(1).unwrap();
+    let b = registry.fork(&mut a, 2).unwrap();
+
+    match registry.seed(1) {
+        Err(ItcError::DuplicateNode(1)) => {},
+        other => panic!("seeded twice: {:?}", other),
+    }
+    match registry.fork(&mut a, 2) {
+        Err(ItcError::DuplicateNode(2)) => {},
+        other => panic!("forked twice: {:?}", other),
+    }
+
+    let mut stranger = NodeStamp { node: 3, stamp: b.stamp.clone() };
+    match registry.fork(&mut stranger, 4) {
+        Err(ItcError::UnknownNode(3)) => {},
+        other => panic!("forked from a stranger: {:?}", other),
+    }
+
+    registry.reclaim(&mut a, b.clone()).unwrap();
+    assert_eq!(a.stamp.id, Ident::One);
+    match registry.reclaim(&mut a, b) {
+        Err(ItcError::UnknownNode(2)) => {},
+        other => panic!("reclaimed twice: {:?}", other),
+    }
+}
+
+#[test]
+fn node_names_are_uuids() {
+    assert_eq!(NodeRegistry::node_name(0x67e55044_10b1_426f_9247_bb680e5fe0c8),
+               "67e55044-10b1-426f-9247-bb680e5fe0c8");
+    assert_eq!(NodeRegistry::node_name(1), "00000000-0000-0000-0000-000000000001");
+}
+
+#[test]
+fn annotations_name_each_region_with_its_count() {
+    let mut registry = NodeRegistry::new();
+    let mut a = registry.seed(0x67e55044_10b1_426f_9247_bb680e5fe0c8).unwrap();
+    let mut b = registry.fork(&mut a, 2).unwrap();
+    let mut c = registry.fork(&mut b, 3).unwrap();
+    a.stamp = a.stamp.event().event().event();
+    c.stamp = c.stamp.event();
+
+    let seen = a.stamp.join(&c.stamp.peek()).event;
+    let table = Stamp::new(a.stamp.id.clone(), seen).annotate_regions(&registry);
+    assert_eq!(table, vec![
+        ("00000000-0000-0000-0000-000000000002".to_string(), 0),
+        ("00000000-0000-0000-0000-000000000003".to_string(), 1),
+        ("67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(), 3),
+    ]);
+
+    // a node holding two regions gets a row for each
+    registry.reclaim(&mut a, c).unwrap();
+    let table = a.stamp.annotate_regions(&registry);
+    assert_eq!(table, vec![
+        ("00000000-0000-0000-0000-000000000002".to_string(), 0),
+        ("67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(), 3),
+        ("67e55044-10b1-426f-9247-bb680e5fe0c8".to_string(), 1),
+    ]);
+}
+
+#[test]
+fn maps_give_each_node_its_highest_count() {
+    let mut registry = NodeRegistry::new();
+    let mut a = registry.seed(1).unwrap();
+    let mut b = registry.fork(&mut a, 2).unwrap();
+    let mut c = registry.fork(&mut b, 3).unwrap();
+    a.stamp = a.stamp.event().event();
+    b.stamp = b.stamp.event();
+
+    let seen = a.stamp.join(&b.stamp.peek()).join(&c.stamp.peek());
+    let map = seen.to_map(&registry);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map[&NodeRegistry::node_name(1)], 2);
+    assert_eq!(map[&NodeRegistry::node_name(2)], 1);
+    assert_eq!(map[&NodeRegistry::node_name(3)], 0);
+
+    // a node holding two regions gets the higher of their counts
+    c.stamp = c.stamp.event().event().event();
+    registry.reclaim(&mut a, c).unwrap();
+    let map = a.stamp.join(&b.stamp.peek()).to_map(&registry);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map[&NodeRegistry::node_name(1)], 3);
+    assert_eq!(map[&NodeRegistry::node_name(2)], 1);
+}
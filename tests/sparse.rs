@@ -0,0 +1,63 @@
+//! Sparse events hold the same trees, and compare and join like them.
+
+extern crate itc;
+
+mod common;
+
+use std::collections::BTreeMap;
+
+use itc::{Event, Ident, Stamp};
+
+use common::{Rng, random_event};
+
+#[test]
+fn sparse_events_round_trip() {
+    let mut rng = Rng(267);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6);
+        let sparse = e.to_sparse();
+        assert_eq!(sparse.to_event().encode(), e.norm().encode(), "sparsifying {:?}", e);
+        assert_eq!(sparse.to_event().to_sparse(), sparse);
+
+        // the base covers the most space, the lowest such value on ties
+        let mut shares = BTreeMap::new();
+        for (path, v) in e.norm().leaf_values() {
+            *shares.entry(v).or_insert(0.0) += 0.5f64.powi(path.depth() as i32);
+        }
+        let widest = shares.values().cloned().fold(0.0, f64::max);
+        assert_eq!(Some(&sparse.base()), shares.iter().find(|&(_, &s)| s == widest).map(|(v, _)| v));
+
+        for pair in sparse.exceptions().windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+        }
+        assert!(sparse.exceptions().iter().all(|&(_, offset)| offset != 0));
+    }
+}
+
+#[test]
+fn sparse_events_compare_and_join_like_trees() {
+    let mut rng = Rng(2267);
+
+    for _ in 0..2000 {
+        let (a, b) = (random_event(&mut rng, 6).norm(), random_event(&mut rng, 6).norm());
+        let (sa, sb) = (a.to_sparse(), b.to_sparse());
+        let (ta, tb) = (Stamp::new(Ident::One, a.clone()), Stamp::new(Ident::One, b.clone()));
+
+        assert_eq!(sa.leq(&sb), a.leq(&b), "comparing {:?} with {:?}", a, b);
+        assert_eq!(sa.causal_cmp(&sb), ta.causal_cmp(&tb), "comparing {:?} with {:?}", a, b);
+        assert_eq!(sa.join(&sb).to_event().encode(), a.join(&b).encode());
+    }
+}
+
+#[test]
+fn a_steady_clock_has_one_exception() {
+    let mut members = Stamp::seed().fork_shards(16);
+    members[5] = members[5].event();
+    let seen = members.iter().fold(Event::seed(), |all, m| all.join(&m.event));
+
+    let sparse = seen.to_sparse();
+    assert_eq!(sparse.base(), 0);
+    assert_eq!(sparse.exceptions().len(), 1);
+    assert_eq!(sparse.exceptions()[0].1, 1);
+}
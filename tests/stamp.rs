@@ -0,0 +1,494 @@
+//! Stamp operations as seen through their ids and events.
+
+extern crate itc;
+
+mod common;
+
+use itc::{CompactPolicy, Event, Ident, IdentPath, ItcError, Side, Stamp};
+
+use common::{Rng, balanced_event, id, leaf, node, owns, random_event, random_ident,
+             slot_ident, value_at};
+
+// The slots `id` owns when the id space is cut into `2^depth` of them.
+fn slots(id: &Ident, depth: u32) -> Vec<u64> {
+    (0..1 << depth).filter(|&i| owns(id, depth, i)).collect()
+}
+
+#[test]
+fn shards_deal_out_the_owned_slots_in_order() {
+    let mut rng = Rng(237);
+
+    for _ in 0..500 {
+        let id = random_ident(&mut rng, 4).canonical();
+        let stamp = Stamp::new(id.clone(), random_event(&mut rng, 4).norm());
+        let count = 1 + rng.below(7) as usize;
+        let shards = stamp.fork_shards(count);
+        assert_eq!(shards.len(), count);
+
+        let dealt: Vec<u64> = shards.iter().flat_map(|s| slots(&s.id, 8)).collect();
+        assert_eq!(dealt, slots(&id, 8), "{} shards of {:?}", count, id);
+        for s in &shards {
+            assert_eq!(s.event, stamp.event);
+        }
+        if id.fork_capacity(8) >= count as u64 {
+            assert!(shards.iter().all(|s| s.id != Ident::Zero), "{} shards of {:?}", count, id);
+        }
+    }
+
+    let shards = Stamp::seed().fork_shards(5);
+    let dealt: Vec<Vec<u64>> = shards.iter().map(|s| slots(&s.id, 3)).collect();
+    assert_eq!(dealt, vec![vec![0], vec![1, 2], vec![3], vec![4, 5], vec![6, 7]]);
+    assert!(Stamp::seed().fork_shards(0).is_empty());
+}
+
+#[test]
+fn fork_compact_shrinks_hand_built_stamps() {
+    let mut rng = Rng(216);
+    let mut shrunk = 0;
+
+    for _ in 0..500 {
+        let event = random_event(&mut rng, 6);
+        let stamp = Stamp::new(id(Ident::One, Ident::One), event.clone());
+        let (a, b) = stamp.fork_compact();
+
+        for half in &[&a, &b] {
+            assert_eq!(half.event.size(), event.norm().size());
+            assert!(half.event.size() <= event.size());
+            assert_eq!(half.event, event);
+        }
+        assert_eq!((a.id, b.id), Ident::One.split());
+
+        if a.event.size() < event.size() {
+            shrunk += 1;
+        }
+    }
+
+    assert!(shrunk > 0);
+}
+
+#[test]
+fn fork_compact_matches_fork_on_compact_stamps() {
+    let (a, b) = Stamp::seed().event().fork();
+    let a = a.event().join(&b.event());
+
+    let (x, y) = a.fork();
+    let (cx, cy) = a.fork_compact();
+    assert_eq!((x.id, x.event.encode()), (cx.id, cx.event.encode()));
+    assert_eq!((y.id, y.event.encode()), (cy.id, cy.event.encode()));
+}
+
+// Normal, with depth 4, size 9 and five leaves, so an ideal depth of 3.
+fn chain() -> Event {
+    node(0, leaf(1), node(0, leaf(1), node(0, leaf(1), node(0, leaf(0), leaf(1)))))
+}
+
+#[test]
+fn max_depth_triggers_past_the_depth() {
+    let stamp = Stamp::new(Ident::One, chain());
+    assert!(stamp.should_compact(CompactPolicy::MaxDepth(3)));
+    assert!(!stamp.should_compact(CompactPolicy::MaxDepth(4)));
+    assert!(!Stamp::seed().should_compact(CompactPolicy::MaxDepth(0)));
+}
+
+#[test]
+fn max_size_triggers_past_the_size() {
+    let stamp = Stamp::new(Ident::One, chain());
+    assert!(stamp.should_compact(CompactPolicy::MaxSize(8)));
+    assert!(!stamp.should_compact(CompactPolicy::MaxSize(9)));
+    assert!(!Stamp::seed().should_compact(CompactPolicy::MaxSize(1)));
+}
+
+#[test]
+fn max_depth_ratio_triggers_past_the_ratio() {
+    let stamp = Stamp::new(Ident::One, chain());
+    assert!(stamp.should_compact(CompactPolicy::MaxDepthRatio(1.3)));
+    assert!(!stamp.should_compact(CompactPolicy::MaxDepthRatio(1.34)));
+    assert!(!Stamp::seed().should_compact(CompactPolicy::MaxDepthRatio(0.0)));
+}
+
+#[test]
+fn shrink_meets_every_policy() {
+    let policies = [
+        CompactPolicy::MaxDepth(2),
+        CompactPolicy::MaxSize(5),
+        CompactPolicy::MaxDepthRatio(1.0),
+    ];
+    let mut rng = Rng(206);
+
+    for _ in 0..500 {
+        let stamp = Stamp::new(Ident::One, random_event(&mut rng, 8).norm());
+
+        for &policy in &policies {
+            let shrunk = stamp.shrink(policy);
+            assert!(!shrunk.should_compact(policy), "shrinking {:?} to {:?}", stamp, policy);
+            assert!(stamp.event.leq(&shrunk.event));
+
+            if !stamp.should_compact(policy) {
+                assert_eq!(shrunk.event.encode(), stamp.event.encode());
+            }
+        }
+    }
+
+    let stamp = Stamp::new(Ident::One, chain());
+    // Flattening the deepest node lets the whole chain collapse.
+    assert_eq!(stamp.shrink(CompactPolicy::MaxDepth(3)).event, leaf(1));
+    assert_eq!(stamp.shrink(CompactPolicy::MaxSize(0)).event, leaf(1));
+}
+
+#[test]
+fn concurrency_witness_names_the_regions_each_side_is_ahead() {
+    let (a, b) = Stamp::seed().fork();
+    let (a, b) = (a.event(), b.event().event());
+
+    let (ahead, behind) = a.concurrency_witness(&b).unwrap();
+    assert_eq!(ahead.steps(), &[Side::Left]);
+    assert_eq!(behind.steps(), &[Side::Right]);
+    assert!(a.concurrency_witness(&a.join(&b)).is_none());
+}
+
+// The first of the `2^depth` slots that `path` covers.
+fn first_slot(path: &IdentPath, depth: u32) -> u64 {
+    path.steps().iter().enumerate()
+        .filter(|&(_, side)| *side == Side::Right)
+        .map(|(level, _)| 1 << (depth as usize - 1 - level))
+        .sum()
+}
+
+#[test]
+fn concurrency_witness_agrees_with_pointwise_values() {
+    let mut rng = Rng(203);
+
+    for _ in 0..2000 {
+        let a = Stamp::new(random_ident(&mut rng, 3).canonical(),
+                           random_event(&mut rng, 5).norm());
+        let b = Stamp::new(random_ident(&mut rng, 3).canonical(),
+                           random_event(&mut rng, 5).norm());
+
+        match a.concurrency_witness(&b) {
+            None => assert!(!a.concurrent(&b), "{:?} and {:?}", a, b),
+            Some((ahead, behind)) => {
+                assert!(a.concurrent(&b));
+                let i = first_slot(&ahead, 6);
+                assert!(value_at(&a.event, 6, i) > value_at(&b.event, 6, i));
+                let i = first_slot(&behind, 6);
+                assert!(value_at(&a.event, 6, i) < value_at(&b.event, 6, i));
+            },
+        }
+    }
+}
+
+// The event over one half of the region covered by `e`.
+fn half(e: &Event, side: Side) -> Event {
+    match *e {
+        Event::Leaf(n) => leaf(n),
+        Event::Node(n, ref l, ref r) => {
+            let child = if side == Side::Left { l } else { r };
+            match **child {
+                Event::Leaf(m) => leaf(n + m),
+                Event::Node(m, ref cl, ref cr) => node(n + m, (**cl).clone(), (**cr).clone()),
+            }
+        },
+    }
+}
+
+// Swaps the halves of the id at random levels, taking the event along.
+fn mirror(rng: &mut Rng, i: &Ident, e: &Event) -> (Ident, Event) {
+    match *i {
+        Ident::Tuple(ref l, ref r) => {
+            let (il, el) = mirror(rng, l, &half(e, Side::Left));
+            let (ir, er) = mirror(rng, r, &half(e, Side::Right));
+            if rng.below(2) == 0 {
+                (id(ir, il), node(0, er, el))
+            } else {
+                (id(il, ir), node(0, el, er))
+            }
+        },
+        _ => (i.clone(), e.clone()),
+    }
+}
+
+#[test]
+fn mirror_images_are_topologically_equal() {
+    let mut rng = Rng(222);
+
+    for _ in 0..2000 {
+        let a = Stamp::new(random_ident(&mut rng, 4).canonical(),
+                           random_event(&mut rng, 5).norm());
+        let (i, e) = mirror(&mut rng, &a.id, &a.event);
+        let b = Stamp::new(i.canonical(), e.norm());
+        assert!(Stamp::topologically_equal(&a, &b), "{:?} and {:?}", a, b);
+
+        if b.id == Ident::Zero {
+            continue;
+        }
+        let ticked = b.event();
+        assert_eq!(Stamp::topologically_equal(&a, &ticked), ticked.event == b.event);
+    }
+
+    let (a, b) = Stamp::seed().fork();
+    assert!(Stamp::topologically_equal(&a.event(), &b.event()));
+    assert!(!Stamp::topologically_equal(&a.event(), &b.event().event()));
+}
+
+#[test]
+fn the_same_label_seeds_identical_stamps() {
+    let a = Stamp::seed_from(b"cluster-a");
+    let b = Stamp::seed_from(b"cluster-a");
+    assert!(a.identical(&b));
+    assert!(!a.identical(&Stamp::seed_from(b"cluster-b")));
+    assert!(a.stamp.identical(&Stamp::seed()));
+
+    // the label survives forks, ticks and joins, and stays out of the encoding
+    let (l, r) = a.fork();
+    let (l, r) = (l.event(), r.event().event());
+    assert_eq!(l.label, a.label);
+    assert_eq!(l.join(&r.stamp).label, a.label);
+    assert!(l.join(&r.stamp).stamp.identical(&l.stamp.join(&r.stamp)));
+    assert_eq!(a.stamp.encode(), Stamp::seed().encode());
+
+    // and a plain stamp can still be built field by field
+    let plain = Stamp { id: Ident::One, event: Event::seed() };
+    assert!(plain.identical(&Stamp::seed()));
+}
+
+#[test]
+fn event_rate_counts_each_region_once() {
+    let mut rng = Rng(232);
+
+    for _ in 0..200 {
+        let i = rng.below(64);
+        let old = Stamp::new(slot_ident(6, i), balanced_event(&mut rng, 6));
+        let new = (0..1 + rng.below(10)).fold(old.clone(), |s, _| s.event());
+
+        // only the owned slot moves, by however much the ticks filled it
+        let moved = value_at(&new.event, 6, i) - value_at(&old.event, 6, i);
+        assert_eq!(Stamp::event_rate(&old, &new, 4.0), moved as f64 / 4.0);
+        assert_eq!(Stamp::event_rate(&new, &old, 4.0), 0.0);
+    }
+
+    let (a, b) = Stamp::seed().fork();
+    let (b, c) = b.fork();
+    let old = a.join(&b.peek()).join(&c.peek());
+    let a = (0..6).fold(a, |s, _| s.event());
+    let b = (0..4).fold(b, |s, _| s.event());
+    let new = a.join(&b.peek()).join(&c.peek());
+    assert_eq!(Stamp::event_rate(&old, &new, 2.0), 5.0);
+    assert_eq!(Stamp::event_rate(&old, &new, 0.0), 0.0);
+    assert_eq!(Stamp::event_rate(&old, &new, -1.0), 0.0);
+
+    // fork halves that tick alike still count once each
+    let tick = |s: &Stamp, n: u32| (0..n).fold(s.clone(), |s, _| s.event());
+    let (a, b) = Stamp::seed().fork();
+    let a = a.event();
+    let old = a.join(&b.peek());
+    let new = tick(&a, 3).join(&tick(&b, 3).peek());
+    assert_eq!(Stamp::event_rate(&old, &new, 1.0), 6.0);
+
+    let shards: Vec<Stamp> = Stamp::seed().fork_shards(4).iter()
+        .enumerate()
+        .map(|(k, s)| tick(s, k as u32))
+        .collect();
+    let seen = |all: &[Stamp]| {
+        all.iter().fold(Stamp::seed().peek(), |seen, s| seen.join(&s.peek()))
+    };
+    let later: Vec<Stamp> = shards.iter().map(|s| tick(s, 2)).collect();
+    assert_eq!(Stamp::event_rate(&seen(&shards), &seen(&later), 1.0), 8.0);
+}
+
+#[test]
+fn catch_up_percent_averages_the_slots() {
+    let mut rng = Rng(234);
+
+    for _ in 0..2000 {
+        let stamp = Stamp::new(Ident::One, random_event(&mut rng, 5).norm());
+        let target = random_event(&mut rng, 5).norm();
+        let percent = stamp.catch_up_percent(&target);
+
+        let caught_up: f64 = (0..256).map(|i| {
+            let (mine, theirs) = (value_at(&stamp.event, 8, i), value_at(&target, 8, i));
+            if mine >= theirs { 1.0 } else { mine as f64 / theirs as f64 }
+        }).sum();
+        assert!((percent - caught_up / 2.56).abs() < 1e-9, "{} of {:?}", percent, target);
+        assert_eq!(percent == 100.0, target.leq(&stamp.event), "{} of {:?}", percent, target);
+    }
+
+    let (a, b) = Stamp::seed().fork();
+    let (a, b) = (a.event().event(), b.event());
+    let target = a.join(&b).event;
+    assert_eq!(a.catch_up_percent(&target), 50.0);
+    assert_eq!(Stamp::seed().catch_up_percent(&target), 0.0);
+    assert_eq!(a.catch_up_percent(&Event::seed()), 100.0);
+}
+
+#[test]
+fn display_shows_the_share_and_event_summary() {
+    assert_eq!(Stamp::seed().to_string(), "id=1 ev[min=0 max=0 size=1]");
+
+    let (a, b) = Stamp::seed().fork();
+    let (b1, b2) = b.fork();
+    assert_eq!(a.join(&b1).event().event().to_string(), "id=3/4 ev[min=0 max=2 size=3]");
+    assert_eq!(b2.peek().to_string(), "id=0 ev[min=0 max=0 size=1]");
+    assert_eq!(b2.event().to_string(), "id=1/4 ev[min=0 max=1 size=5]");
+
+    let shares = Stamp::seed().fork_shards(5);
+    let shown: Vec<String> = shares.iter().map(|s| s.to_string()).collect();
+    assert_eq!(shown[..2], ["id=1/8 ev[min=0 max=0 size=1]", "id=1/4 ev[min=0 max=0 size=1]"]);
+
+    let mut rng = Rng(245);
+    for _ in 0..500 {
+        let e = random_event(&mut rng, 5);
+        let shown = Stamp::new(Ident::One, e.clone()).to_string();
+        assert_eq!(shown, format!("id=1 ev[min={} max={} size={}]", e.min(), e.max(), e.size()));
+    }
+}
+
+#[test]
+fn fast_forward_only_catches_up() {
+    let mut rng = Rng(248);
+
+    for _ in 0..2000 {
+        let before = Stamp::new(random_ident(&mut rng, 3).canonical(),
+                                random_event(&mut rng, 5).norm());
+        let incoming = Stamp::new(Ident::Zero, random_event(&mut rng, 5).norm());
+        let mut after = before.clone();
+
+        match after.fast_forward(&incoming) {
+            Ok(()) => {
+                assert!(!before.concurrent(&incoming));
+                assert!(after.identical(&before.join(&incoming)));
+            },
+            Err(ItcError::Concurrent) => {
+                assert!(before.concurrent(&incoming));
+                assert!(after.identical(&before));
+            },
+            Err(e) => panic!("fast forwarding {:?} to {:?}: {}", before, incoming, e),
+        }
+    }
+
+    let (a, b) = Stamp::seed().fork();
+    let mut a = a.event();
+    let b = b.join(&a.peek()).event();
+    a.fast_forward(&b.peek()).unwrap();
+    assert_eq!(a.event, b.event);
+    assert_eq!(a.id, Ident::One.split().0);
+}
+
+#[test]
+fn rejoined_forks_give_back_the_parent_id() {
+    let (a, b) = Stamp::seed().fork();
+    assert_eq!(a.join(&b).id, Ident::One);
+
+    // a deep id split over many cycles stays the size it started at
+    let mut rest = Stamp::seed().fork_shards(3);
+    let mut kept = rest.remove(0);
+    let size = kept.id.encoded_len();
+    for _ in 0..1000 {
+        let (x, y) = kept.fork();
+        let (y1, y2) = y.event().fork();
+        kept = x.event().join(&y2.event()).join(&y1);
+        assert_eq!(kept.id.encoded_len(), size);
+    }
+    let all = rest.iter().fold(kept, |all, s| all.join(s));
+    assert_eq!(all.id, Ident::One);
+
+    // so does a cluster churning at random, once everyone is back
+    let mut rng = Rng(250);
+    for _ in 0..50 {
+        let mut members = vec![Stamp::seed()];
+        for _ in 0..100 {
+            let k = rng.below(members.len() as u64) as usize;
+            if members.len() > 1 && rng.below(2) == 0 {
+                let departing = members.swap_remove(k);
+                let heir = rng.below(members.len() as u64) as usize;
+                members[heir] = members[heir].join(&departing);
+            } else {
+                let (x, y) = members[k].event().fork();
+                members[k] = x;
+                members.push(y);
+            }
+        }
+        let all = members.iter().fold(Stamp::new(Ident::Zero, Event::seed()), |a, s| a.join(s));
+        assert_eq!(all.id, Ident::One);
+    }
+}
+
+#[test]
+fn receive_learning_names_the_regions_that_advanced() {
+    let mut rng = Rng(266);
+
+    for _ in 0..2000 {
+        let mine = random_event(&mut rng, 5).norm();
+        let theirs = random_event(&mut rng, 5).norm();
+        let mut stamp = Stamp::new(Ident::One, mine.clone());
+        let learned = stamp.receive_learning(&Stamp::new(Ident::Zero, theirs.clone()));
+
+        for i in 0..256 {
+            let covering = learned.iter().filter(|p| {
+                let first = first_slot(p, 8);
+                first <= i && i < first + (1 << (8 - p.depth()))
+            }).count();
+            let advanced = value_at(&theirs, 8, i) > value_at(&mine, 8, i);
+            assert_eq!(covering, advanced as usize, "{:?} into {:?}", theirs, mine);
+        }
+
+        // left to right, with no pair of siblings left unmerged
+        for pair in learned.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert_ne!(pair[0].steps().split_last().map(|(_, up)| up),
+                       pair[1].steps().split_last().map(|(_, up)| up));
+        }
+        assert_eq!(stamp.event.encode(), mine.join(&theirs).encode());
+        assert_eq!(stamp.id, Ident::One);
+    }
+
+    let mut stamp = Stamp::seed();
+    assert_eq!(stamp.receive_learning(&Stamp::seed().event().peek()), vec![IdentPath::root()]);
+    assert!(stamp.receive_learning(&Stamp::seed().peek()).is_empty());
+}
+
+#[test]
+fn valid_stamps_pass_the_debug_check() {
+    let (a, b) = Stamp::seed().fork();
+    let (b, c) = b.event().fork();
+    a.event().join(&c.event()).debug_assert_valid();
+    b.debug_assert_valid();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "stamp id is not canonical")]
+fn debug_check_catches_a_non_canonical_id() {
+    Stamp::new(id(Ident::One, Ident::One), leaf(0)).debug_assert_valid();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "stamp event is not normalized")]
+fn debug_check_catches_an_unnormalized_event() {
+    Stamp::new(Ident::One, node(0, leaf(1), leaf(1))).debug_assert_valid();
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic(expected = "stamp event counts below zero")]
+fn debug_check_catches_a_negative_count() {
+    Stamp::new(Ident::One, node(-1, leaf(0), leaf(2))).debug_assert_valid();
+}
+
+#[test]
+fn stamps_cross_threads_as_their_encoding() {
+    let (a, b) = Stamp::seed().event().fork();
+    let sent = b.encode();
+
+    let returned = std::thread::spawn(move || {
+        let b = Stamp::decode(&sent).unwrap();
+        (0..3).fold(b, |b, _| b.event()).encode()
+    }).join().unwrap();
+
+    let b = Stamp::decode(&returned).unwrap();
+    assert_eq!((b.event.min(), b.event.max()), (1, 4));
+    assert!(a.leq(&b) && !b.leq(&a));
+    let all = a.join(&b);
+    assert_eq!(all.id, Ident::One);
+    assert_eq!(all.event.max(), 4);
+}
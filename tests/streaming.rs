@@ -0,0 +1,75 @@
+//! Streaming over encodings agrees with working on the events they encode:
+//! `leq_streaming` with `Event::leq`, and `merge_stream` with `merge_all`.
+
+extern crate itc;
+
+mod common;
+
+use std::io::BufReader;
+
+use itc::{DecodeError, Event, leq_streaming};
+
+use common::{Rng, leaf, node, random_event};
+
+#[test]
+fn agrees_with_leq() {
+    let mut rng = Rng(12345);
+
+    for _ in 0..2000 {
+        let a = random_event(&mut rng, 6);
+        let b = random_event(&mut rng, 6);
+
+        for &(x, y) in &[(&a, &b), (&b, &a), (&a, &a)] {
+            let streamed = leq_streaming(&x.encode()[..], &y.encode()[..]).unwrap();
+            assert_eq!(streamed, x.leq(y), "comparing {:?} with {:?}", x, y);
+        }
+    }
+}
+
+#[test]
+fn rejects_overflowing_offsets() {
+    let huge = node(i64::MAX, node(i64::MAX, leaf(1), leaf(0)), leaf(0));
+    let small = leaf(0).encode();
+
+    match leq_streaming(&huge.encode()[..], &small[..]) {
+        Err(DecodeError::Overflow) => {},
+        other => panic!("expected an overflow, got {:?}", other),
+    }
+
+    match leq_streaming(&small[..], &huge.encode()[..]) {
+        Err(DecodeError::Overflow) => {},
+        other => panic!("expected an overflow, got {:?}", other),
+    }
+}
+
+#[test]
+fn merge_stream_agrees_with_merge_all() {
+    let mut rng = Rng(246);
+    let events: Vec<Event> = (0..1000).map(|_| random_event(&mut rng, 6)).collect();
+    let mut bytes = Vec::new();
+    for e in &events {
+        e.encode_to(&mut bytes).unwrap();
+    }
+
+    // a tiny buffer makes encodings straddle refills
+    let mut checkpoints = Vec::new();
+    let merged = Event::merge_stream(BufReader::with_capacity(7, &bytes[..]), 100,
+                                     |count, e| checkpoints.push((count, e.clone()))).unwrap();
+    assert_eq!(merged.encode(), Event::merge_all(&events).encode());
+
+    assert_eq!(checkpoints.len(), 10);
+    for (k, &(count, ref e)) in checkpoints.iter().enumerate() {
+        assert_eq!(count, 100 * (k as u64 + 1));
+        assert_eq!(e.encode(), Event::merge_all(&events[..count as usize]).encode());
+    }
+
+    assert_eq!(Event::merge_stream(&[][..], 1, |_, _| ()).unwrap().encode(),
+               Event::seed().encode());
+
+    // cutting a node short leaves its children missing
+    node(0, leaf(1), leaf(2)).encode_to(&mut bytes).unwrap();
+    match Event::merge_stream(&bytes[..bytes.len() - 1], 0, |_, _| ()) {
+        Err(DecodeError::Truncated) => {},
+        other => panic!("merged a truncated stream to {:?}", other),
+    }
+}
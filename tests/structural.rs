@@ -0,0 +1,69 @@
+//! `Structural` orders ids and events totally and deterministically, agreeing
+//! with equality.
+
+extern crate itc;
+
+mod common;
+
+use std::cmp::Ordering;
+
+use itc::Structural;
+
+use common::{Rng, random_event, random_ident};
+
+#[test]
+fn events_are_totally_ordered() {
+    let mut rng = Rng(215);
+    let events: Vec<_> = (0..300)
+        .map(|_| Structural::from(random_event(&mut rng, 5)))
+        .collect();
+
+    for a in &events {
+        for b in &events {
+            assert_eq!(a.cmp(b), b.cmp(a).reverse());
+            assert_eq!(a.cmp(b) == Ordering::Equal, a.get() == b.get());
+        }
+    }
+
+    let mut sorted = events.clone();
+    sorted.sort();
+    for pair in sorted.windows(2) {
+        assert!(pair[0] <= pair[1]);
+    }
+
+    for a in &sorted {
+        for b in &sorted {
+            for c in &sorted[..20] {
+                if a <= b && b <= c {
+                    assert!(a <= c);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn order_is_stable_across_runs_and_shapes() {
+    let mut rng = Rng(2215);
+    let events: Vec<_> = (0..300).map(|_| random_event(&mut rng, 5)).collect();
+
+    let mut once: Vec<_> = events.iter().cloned().map(Structural::from).collect();
+    let mut again: Vec<_> = events.iter().rev().map(|e| Structural::from(e.norm())).collect();
+    once.sort();
+    again.sort();
+
+    for (a, b) in once.iter().zip(&again) {
+        assert_eq!(a.get().norm().encode(), b.get().norm().encode());
+    }
+}
+
+#[test]
+fn ids_are_ordered_by_the_space_they_own() {
+    let mut rng = Rng(3215);
+
+    for _ in 0..1000 {
+        let id = random_ident(&mut rng, 5);
+        assert_eq!(Structural::from(id.clone()), Structural::from(id.canonical()));
+        assert_eq!(Structural::from(id.clone()).into_inner(), id);
+    }
+}
@@ -0,0 +1,120 @@
+//! Ticking events, in place and functionally, gives the same trees, and in
+//! place ticks reuse the nodes no other event shares.
+
+extern crate itc;
+
+mod common;
+
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use itc::{Event, Ident};
+
+use common::{Rng, balanced_event, id, random_event, random_ident, slot_ident};
+
+#[test]
+fn event_in_place_matches_event() {
+    let mut rng = Rng(258);
+
+    for _ in 0..3000 {
+        let id = random_ident(&mut rng, 5).canonical();
+        let mut e = random_event(&mut rng, 5).norm();
+
+        if id == Ident::Zero {
+            continue;
+        }
+
+        let mut expected = e.clone();
+
+        for _ in 0..6 {
+            expected = expected.event(&id);
+            e.event_in_place(&id);
+            assert_eq!(e.encode(), expected.encode(), "ticking with {:?}", id);
+        }
+    }
+}
+
+#[test]
+fn event_in_place_leaves_shared_trees_alone() {
+    let mut rng = Rng(2258);
+
+    for _ in 0..500 {
+        let id = random_ident(&mut rng, 5).canonical();
+        let e = random_event(&mut rng, 5).norm();
+
+        if id == Ident::Zero {
+            continue;
+        }
+
+        let before = e.encode();
+        let mut ticked = e.clone();
+        ticked.event_in_place(&id);
+        assert_eq!(e.encode(), before);
+    }
+}
+
+// The addresses of every node below the root.
+fn nodes(e: &Event) -> HashSet<*const Event> {
+    fn visit(e: &Rc<Event>, seen: &mut HashSet<*const Event>) {
+        seen.insert(Rc::as_ptr(e));
+        if let Event::Node(_, ref l, ref r) = **e {
+            visit(l, seen);
+            visit(r, seen);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    if let Event::Node(_, ref l, ref r) = *e {
+        visit(l, &mut seen);
+        visit(r, &mut seen);
+    }
+    seen
+}
+
+#[test]
+fn ticking_an_unshared_tree_reuses_its_nodes() {
+    let mut rng = Rng(3258);
+
+    for _ in 0..100 {
+        let mut e = balanced_event(&mut rng, 6);
+        let before = nodes(&e);
+        let quarters = id(id(Ident::One, Ident::Zero), id(Ident::Zero, Ident::One));
+
+        // the first ticks fill the owned quarters and the rest grow their leaves
+        for _ in 0..20 {
+            let expected = e.event(&quarters);
+            e.event_in_place(&quarters);
+            assert_eq!(e.encode(), expected.encode());
+            assert!(nodes(&e).is_subset(&before), "ticking allocated in {:?}", e);
+        }
+    }
+}
+
+#[test]
+fn ticking_a_shared_tree_copies_only_the_path() {
+    let mut rng = Rng(4258);
+
+    for _ in 0..100 {
+        let e = balanced_event(&mut rng, 6);
+        let slot = slot_ident(6, rng.below(64));
+        let mut ticked = e.clone();
+        ticked.event_in_place(&slot);
+        assert_eq!(ticked.encode(), e.event(&slot).encode());
+
+        // the path and the siblings whose values sink, and a split leaf
+        let copied = nodes(&ticked).difference(&nodes(&e)).count();
+        assert!(copied <= 2 * 6 + 2, "{} nodes copied ticking {:?}", copied, e);
+    }
+
+    // a tick that changes nothing under a shared child leaves it shared
+    let e = Event::node(0, Event::node(0, Event::leaf(0), Event::leaf(2)), Event::leaf(1));
+    let mut ticked = e.clone();
+    ticked.event_in_place(&id(Ident::Zero, Ident::One));
+    match (&e, &ticked) {
+        (Event::Node(_, l, _), Event::Node(_, m, _)) => {
+            assert!(Rc::ptr_eq(l, m));
+            assert_eq!(Rc::strong_count(l), 2);
+        },
+        _ => panic!("ticked into {:?}", ticked),
+    }
+}
@@ -0,0 +1,79 @@
+//! Tracked events keep extrema that match walking the tree, and a tick only
+//! rebuilds the path down to the region it raises.
+
+extern crate itc;
+
+mod common;
+
+use itc::{Event, Stamp, TrackedEvent};
+
+use common::{Rng, Step, apply_step, balanced_event, random_event, random_steps, slot_ident};
+
+#[test]
+fn tracked_extrema_follow_churn() {
+    let mut rng = Rng(241);
+
+    for _ in 0..50 {
+        let mut stamps = vec![Stamp::seed()];
+        let mut tracked = vec![TrackedEvent::new(&Event::seed())];
+
+        for step in random_steps(&mut rng, 200) {
+            match step {
+                Step::Fork(k) => {
+                    let copy = tracked[k].clone();
+                    tracked.push(copy);
+                },
+                Step::Tick(k) => tracked[k].tick(&stamps[k].id),
+                Step::Merge(k, j) => {
+                    let other = tracked[j].clone();
+                    tracked[k].join(&other);
+                },
+                Step::Leave(k, j) => {
+                    let departing = tracked.swap_remove(k);
+                    tracked[j].join(&departing);
+                },
+            }
+            apply_step(&mut stamps, step);
+
+            for (s, t) in stamps.iter().zip(&tracked) {
+                assert_eq!(t.event().encode(), s.event.encode());
+                assert_eq!(t.max(), s.event.max(), "in {:?}", s.event);
+                assert_eq!(t.min(), s.event.min(), "in {:?}", s.event);
+            }
+        }
+    }
+}
+
+#[test]
+fn tracking_starts_from_the_normal_form() {
+    let mut rng = Rng(2241);
+
+    for _ in 0..2000 {
+        let e = random_event(&mut rng, 6);
+        let tracked = TrackedEvent::new(&e);
+        assert_eq!(tracked.event().encode(), e.norm().encode());
+        assert_eq!((tracked.min(), tracked.max()), (e.min(), e.max()), "in {:?}", e);
+    }
+}
+
+#[test]
+fn ticks_rebuild_only_the_path_to_the_slot() {
+    let mut rng = Rng(3241);
+
+    for &depth in &[6, 10, 14] {
+        let before = balanced_event(&mut rng, depth);
+        let mut tracked = TrackedEvent::new(&before);
+
+        for _ in 0..20 {
+            let id = slot_ident(depth, rng.below(1 << depth));
+            let last = tracked.event().clone();
+            tracked.tick(&id);
+
+            let built = Event::unique_node_count(&[&last, tracked.event()]) -
+                Event::unique_node_count(&[&last]);
+            assert!(built <= 2 * depth as usize + 2, "{} nodes built at depth {}", built, depth);
+            assert_eq!(tracked.event().encode(), last.event(&id).encode());
+            assert_eq!(tracked.max(), tracked.event().max());
+        }
+    }
+}
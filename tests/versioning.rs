@@ -0,0 +1,79 @@
+//! Versioned encodings round trip in every version, and name their version.
+//! V2 only ever saves space.
+
+extern crate itc;
+
+mod common;
+
+use itc::{DecodeError, EncodingVersion, Event};
+
+use common::{Rng, leaf, node, random_event};
+
+#[test]
+fn every_version_round_trips() {
+    let mut rng = Rng(238);
+
+    for _ in 0..1000 {
+        let e = random_event(&mut rng, 8);
+
+        for &version in &[EncodingVersion::V1, EncodingVersion::V2] {
+            let (decoded, found) = Event::decode_versioned(&e.encode_versioned(version))
+                .unwrap();
+            assert_eq!(found, version);
+            assert_eq!(decoded.encode(), e.norm().encode(), "decoding {:?} as {:?}", e, version);
+        }
+    }
+}
+
+#[test]
+fn unknown_versions_are_rejected() {
+    let mut bytes = Event::Leaf(3).encode_versioned(EncodingVersion::V1);
+
+    for &tag in &[0, 3, 0xff] {
+        bytes[0] = tag;
+        match Event::decode_versioned(&bytes) {
+            Err(DecodeError::UnsupportedVersion(t)) => assert_eq!(t, tag),
+            other => panic!("tag {} decoded as {:?}", tag, other),
+        }
+    }
+}
+
+#[test]
+fn empty_input_is_truncated() {
+    match Event::decode_versioned(&[]) {
+        Err(DecodeError::Truncated) => {},
+        other => panic!("empty input decoded as {:?}", other),
+    }
+}
+
+// A tree in normal form whose two halves are the same at every level.
+fn symmetric(depth: u32) -> Event {
+    if depth == 0 {
+        node(0, leaf(0), leaf(1))
+    } else {
+        node(0, symmetric(depth - 1), symmetric(depth - 1))
+    }
+}
+
+#[test]
+fn v2_writes_identical_halves_once() {
+    let e = symmetric(12);
+    assert!(Event::is_minimal_encoding(&e.encode()));
+
+    let v1 = e.encode_versioned(EncodingVersion::V1);
+    let v2 = e.encode_versioned(EncodingVersion::V2);
+    assert!(v1.len() > 8000, "{} bytes", v1.len());
+    assert!(v2.len() < 20, "{} bytes", v2.len());
+    assert_eq!(Event::decode_versioned(&v2).unwrap().0.encode(), e.encode());
+
+    // and it never costs more than V1
+    let mut rng = Rng(243);
+    for _ in 0..1000 {
+        let e = random_event(&mut rng, 7).norm();
+        let v2 = e.encode_versioned(EncodingVersion::V2);
+        assert!(v2.len() <= e.encode_versioned(EncodingVersion::V1).len(), "encoding {:?}", e);
+        for k in 1..v2.len() {
+            assert!(Event::decode_versioned(&v2[..k]).is_err());
+        }
+    }
+}